@@ -7,4 +7,12 @@ fn main() {
         .include(&dir)
         .file(dir.join("parser.c"))
         .compile("tree-sitter-java");
+
+    let kotlin_dir: PathBuf = ["upstream", "tree-sitter-kotlin", "src"].iter().collect();
+
+    cc::Build::new()
+        .include(&kotlin_dir)
+        .file(kotlin_dir.join("parser.c"))
+        .file(kotlin_dir.join("scanner.c"))
+        .compile("tree-sitter-kotlin");
 }
@@ -0,0 +1,278 @@
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+
+use std::io;
+use std::io::{BufRead, Read};
+
+/// The most input a frame may hold. An unterminated value is cut off here rather than buffering
+/// the rest of the stream, and the input skipped up to the next line starting with `[`.
+const MAX_FRAME_LEN: usize = 64 * 1024 * 1024;
+
+/// A piece of a channel's input stream.
+#[derive(Debug, PartialEq)]
+pub enum Frame {
+    /// The text of one top-level JSON array or object. Its brackets balance, but it may still
+    /// fail to parse.
+    Value(Vec<u8>),
+    /// Text that cannot be a message: stray input between values, or a value cut short by a
+    /// mismatched bracket or by the start of the next message. Text past the frame size limit is
+    /// left out.
+    Malformed(Vec<u8>),
+}
+
+/// Splits the input of a vim channel into top-level values by matching brackets, so that a
+/// malformed message costs only itself. Vim starts each message on a new line, so a line
+/// starting with `[` where the unfinished value cannot go on with an array ends that value and
+/// begins the next. Where it can, as in a pretty-printed message, the line is read as part of it.
+pub struct FrameReader<R: BufRead> {
+    input: io::Bytes<R>,
+    peeked: Option<u8>,
+    max_len: usize,
+}
+
+impl<R: BufRead> FrameReader<R> {
+    pub fn new(input: R) -> Self {
+        FrameReader {
+            input: input.bytes(),
+            peeked: None,
+            max_len: MAX_FRAME_LEN,
+        }
+    }
+
+    /// Limits frames to `max_len` bytes instead of `MAX_FRAME_LEN`.
+    pub fn with_max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len;
+        self
+    }
+
+    fn next_byte(&mut self) -> io::Result<Option<u8>> {
+        match self.peeked.take() {
+            Some(b) => Ok(Some(b)),
+            None => self.input.next().transpose(),
+        }
+    }
+
+    /// Collects stray text up to the end of its line or the start of a value.
+    fn read_stray(&mut self, first: u8) -> io::Result<Frame> {
+        let mut text = vec![first];
+        while let Some(b) = self.next_byte()? {
+            match b {
+                b'\n' => break,
+                b'[' | b'{' => {
+                    self.peeked = Some(b);
+                    break;
+                }
+                _ if text.len() < self.max_len => text.push(b),
+                _ => {}
+            }
+        }
+        Ok(Frame::Malformed(text))
+    }
+
+    /// Drops input up to the next line starting with `[`, which begins the next message.
+    fn skip_to_next_message(&mut self) -> io::Result<()> {
+        let mut at_line_start = false;
+        while let Some(b) = self.next_byte()? {
+            if at_line_start && b == b'[' {
+                self.peeked = Some(b);
+                break;
+            }
+            at_line_start = b == b'\n';
+        }
+        Ok(())
+    }
+
+    fn read_value(&mut self, opener: u8) -> io::Result<Frame> {
+        let mut text = vec![opener];
+        let mut closers: Vec<u8> = vec![closer_for(opener)];
+        let mut in_string = false;
+        let mut escaped = false;
+        let mut at_line_start = false;
+        // The last byte outside of strings that was not whitespace.
+        let mut last_token = opener;
+        while let Some(b) = self.next_byte()? {
+            // Strings cannot span lines, and an array can only start where a value may.
+            let is_broken_off = in_string || !matches!(last_token, b'[' | b',' | b':');
+            if at_line_start && b == b'[' && is_broken_off {
+                self.peeked = Some(b);
+                return Ok(Frame::Malformed(text));
+            }
+            at_line_start = b == b'\n';
+            if text.len() >= self.max_len {
+                self.skip_to_next_message()?;
+                return Ok(Frame::Malformed(text));
+            }
+            text.push(b);
+            if in_string {
+                match b {
+                    _ if escaped => escaped = false,
+                    b'\\' => escaped = true,
+                    b'"' => {
+                        in_string = false;
+                        last_token = b;
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+            if !b.is_ascii_whitespace() {
+                last_token = b;
+            }
+            match b {
+                b'"' => in_string = true,
+                b'[' | b'{' => closers.push(closer_for(b)),
+                b']' | b'}' => {
+                    if closers.pop() != Some(b) {
+                        // The rest of the line belongs to the broken message.
+                        while let Some(b) = self.next_byte()? {
+                            if b == b'\n' {
+                                break;
+                            }
+                            if text.len() < self.max_len {
+                                text.push(b);
+                            }
+                        }
+                        return Ok(Frame::Malformed(text));
+                    }
+                    if closers.is_empty() {
+                        return Ok(Frame::Value(text));
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(Frame::Malformed(text))
+    }
+}
+
+fn closer_for(opener: u8) -> u8 {
+    if opener == b'[' {
+        b']'
+    } else {
+        b'}'
+    }
+}
+
+impl<R: BufRead> Iterator for FrameReader<R> {
+    type Item = io::Result<Frame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let b = match self.next_byte() {
+                Ok(Some(b)) => b,
+                Ok(None) => return None,
+                Err(e) => return Some(Err(e)),
+            };
+            return Some(match b {
+                _ if b.is_ascii_whitespace() => continue,
+                b'[' | b'{' => self.read_value(b),
+                _ => self.read_stray(b),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frames(input: &str) -> Vec<Frame> {
+        frames_with_max_len(input, MAX_FRAME_LEN)
+    }
+
+    fn frames_with_max_len(input: &str, max_len: usize) -> Vec<Frame> {
+        FrameReader::new(input.as_bytes())
+            .with_max_len(max_len)
+            .collect::<io::Result<Vec<Frame>>>()
+            .unwrap()
+    }
+
+    fn value(text: &str) -> Frame {
+        Frame::Value(text.as_bytes().to_vec())
+    }
+
+    fn malformed(text: &str) -> Frame {
+        Frame::Malformed(text.as_bytes().to_vec())
+    }
+
+    #[test]
+    fn values_on_one_line_are_split() {
+        assert_eq!(
+            frames("[1,\"a\"][2,{\"b\":[]}]\n"),
+            vec![value("[1,\"a\"]"), value("[2,{\"b\":[]}]")]
+        );
+    }
+
+    #[test]
+    fn brackets_and_escaped_quotes_in_strings_are_text() {
+        let text = r#"[1,"a]b[c\"d{\\",{"k":"}\""}]"#;
+        assert_eq!(frames(text), vec![value(text)]);
+    }
+
+    #[test]
+    fn truncated_value_ends_at_a_line_starting_with_a_bracket() {
+        assert_eq!(
+            frames("[1,{\"type\":\"Foo\"\n[2,\"x\"]\n"),
+            vec![malformed("[1,{\"type\":\"Foo\"\n"), value("[2,\"x\"]")]
+        );
+        assert_eq!(
+            frames("[1,{\"type\":\"Fo\n[2]"),
+            vec![malformed("[1,{\"type\":\"Fo\n"), value("[2]")]
+        );
+    }
+
+    #[test]
+    fn pretty_printed_nested_arrays_stay_in_their_value() {
+        assert_eq!(
+            frames("[1,\n[2,3]]\n[4]"),
+            vec![value("[1,\n[2,3]]"), value("[4]")]
+        );
+        let text = "[\n  5,\n  {\n    \"names\": [\n      \"a\",\n      \"b\"\n    ],\n    \"pairs\":\n[[1,2],\n[3,4]]\n  }\n]";
+        assert_eq!(frames(text), vec![value(text)]);
+    }
+
+    #[test]
+    fn mismatched_closer_takes_the_rest_of_its_line() {
+        assert_eq!(
+            frames("[1,{\"a\":1]] tail\n[2]"),
+            vec![malformed("[1,{\"a\":1]] tail"), value("[2]")]
+        );
+    }
+
+    #[test]
+    fn stray_text_between_values_is_malformed() {
+        assert_eq!(
+            frames("[1] junk\n  more[2]"),
+            vec![
+                value("[1]"),
+                malformed("junk"),
+                malformed("more"),
+                value("[2]")
+            ]
+        );
+    }
+
+    #[test]
+    fn value_cut_off_by_the_end_of_input_is_malformed() {
+        assert_eq!(frames("[1,\"abc"), vec![malformed("[1,\"abc")]);
+        assert_eq!(frames("[1,[2]"), vec![malformed("[1,[2]")]);
+    }
+
+    #[test]
+    fn oversized_value_is_cut_off_and_skipped() {
+        let input = format!("[1,\"{}\n{}\n[2]\n", "a".repeat(100), "b".repeat(100));
+        assert_eq!(
+            frames_with_max_len(&input, 8),
+            vec![malformed("[1,\"aaaa"), value("[2]")]
+        );
+    }
+
+    #[test]
+    fn oversized_stray_text_is_cut_off() {
+        let input = format!("{}\n[2]", "x".repeat(100));
+        assert_eq!(
+            frames_with_max_len(&input, 8),
+            vec![malformed("xxxxxxxx"), value("[2]")]
+        );
+    }
+}
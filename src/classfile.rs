@@ -0,0 +1,390 @@
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+
+use anyhow::{anyhow, Result};
+
+const CLASS_MAGIC: u32 = 0xcafe_babe;
+
+pub const ACC_PUBLIC: u16 = 0x0001;
+pub const ACC_STATIC: u16 = 0x0008;
+pub const ACC_SYNTHETIC: u16 = 0x1000;
+
+/// The constant pool entries this crate reads. Everything else is kept as a placeholder so that
+/// indexes into the pool stay valid.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Constant {
+    Utf8(String),
+    Integer(i32),
+    Class(u16),
+    Module(u16),
+    Package(u16),
+    Other,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Attribute {
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Member {
+    pub access_flags: u16,
+    pub name: String,
+    pub descriptor: String,
+    pub attributes: Vec<Attribute>,
+}
+
+/// The value of an annotation element. Constants keep their tag and pool index since their type
+/// depends on the tag.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ElementValue {
+    Const(u8, u16),
+    Enum(u16, u16),
+    Class(u16),
+    Annotation(Annotation),
+    Array(Vec<ElementValue>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Annotation {
+    /// A field descriptor such as `Lkotlin/Metadata;`.
+    pub type_name: String,
+    pub elements: Vec<(String, ElementValue)>,
+}
+
+impl Annotation {
+    pub fn element(&self, name: &str) -> Option<&ElementValue> {
+        self.elements
+            .iter()
+            .find(|(element_name, _)| element_name == name)
+            .map(|(_, value)| value)
+    }
+}
+
+/// Just enough of a class file to read names, members and attributes. Code is never
+/// interpreted, so method bodies stay opaque attribute bytes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClassFile {
+    pub constants: Vec<Constant>,
+    pub access_flags: u16,
+    pub this_class: u16,
+    pub fields: Vec<Member>,
+    pub methods: Vec<Member>,
+    pub attributes: Vec<Attribute>,
+}
+
+/// A big-endian cursor over class file bytes.
+pub struct ByteReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        ByteReader { bytes, pos: 0 }
+    }
+
+    pub fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| anyhow!("Truncated class file."))?;
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    pub fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn u16(&mut self) -> Result<u16> {
+        let b = self.take(2)?;
+        Ok(u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    pub fn u32(&mut self) -> Result<u32> {
+        let b = self.take(4)?;
+        Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+}
+
+fn read_constants(reader: &mut ByteReader) -> Result<Vec<Constant>> {
+    let count = reader.u16()? as usize;
+    // Entry 0 is unused; indexes into the pool start at 1.
+    let mut constants = vec![Constant::Other];
+    while constants.len() < count {
+        let tag = reader.u8()?;
+        let constant = match tag {
+            1 => {
+                let len = reader.u16()? as usize;
+                // Class files use modified UTF-8, which only differs for NUL and supplementary
+                // characters. Neither matters for names.
+                Constant::Utf8(String::from_utf8_lossy(reader.take(len)?).into_owned())
+            }
+            3 => Constant::Integer(reader.u32()? as i32),
+            4 => {
+                reader.take(4)?;
+                Constant::Other
+            }
+            5 | 6 => {
+                // Longs and doubles take up two pool slots.
+                reader.take(8)?;
+                constants.push(Constant::Other);
+                Constant::Other
+            }
+            7 => Constant::Class(reader.u16()?),
+            8 | 16 => {
+                reader.take(2)?;
+                Constant::Other
+            }
+            9 | 10 | 11 | 12 | 17 | 18 => {
+                reader.take(4)?;
+                Constant::Other
+            }
+            15 => {
+                reader.take(3)?;
+                Constant::Other
+            }
+            19 => Constant::Module(reader.u16()?),
+            20 => Constant::Package(reader.u16()?),
+            _ => return Err(anyhow!("Unknown constant pool tag {}.", tag)),
+        };
+        constants.push(constant);
+    }
+    Ok(constants)
+}
+
+fn utf8_at(constants: &[Constant], idx: u16) -> Result<String> {
+    match constants.get(idx as usize) {
+        Some(Constant::Utf8(s)) => Ok(s.clone()),
+        _ => Err(anyhow!("Constant {} is not a UTF-8 string.", idx)),
+    }
+}
+
+fn read_attributes(reader: &mut ByteReader, constants: &[Constant]) -> Result<Vec<Attribute>> {
+    let count = reader.u16()?;
+    let mut attributes = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let name = utf8_at(constants, reader.u16()?)?;
+        let len = reader.u32()? as usize;
+        attributes.push(Attribute {
+            name,
+            data: reader.take(len)?.to_vec(),
+        });
+    }
+    Ok(attributes)
+}
+
+fn read_members(reader: &mut ByteReader, constants: &[Constant]) -> Result<Vec<Member>> {
+    let count = reader.u16()?;
+    let mut members = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let access_flags = reader.u16()?;
+        let name = utf8_at(constants, reader.u16()?)?;
+        let descriptor = utf8_at(constants, reader.u16()?)?;
+        let attributes = read_attributes(reader, constants)?;
+        members.push(Member {
+            access_flags,
+            name,
+            descriptor,
+            attributes,
+        });
+    }
+    Ok(members)
+}
+
+fn read_element_value(reader: &mut ByteReader, constants: &[Constant]) -> Result<ElementValue> {
+    let tag = reader.u8()?;
+    let value = match tag {
+        b'B' | b'C' | b'D' | b'F' | b'I' | b'J' | b'S' | b'Z' | b's' => {
+            ElementValue::Const(tag, reader.u16()?)
+        }
+        b'e' => ElementValue::Enum(reader.u16()?, reader.u16()?),
+        b'c' => ElementValue::Class(reader.u16()?),
+        b'@' => ElementValue::Annotation(read_annotation(reader, constants)?),
+        b'[' => {
+            let count = reader.u16()?;
+            let mut values = Vec::with_capacity(count as usize);
+            for _ in 0..count {
+                values.push(read_element_value(reader, constants)?);
+            }
+            ElementValue::Array(values)
+        }
+        _ => return Err(anyhow!("Unknown element value tag {}.", tag)),
+    };
+    Ok(value)
+}
+
+fn read_annotation(reader: &mut ByteReader, constants: &[Constant]) -> Result<Annotation> {
+    let type_name = utf8_at(constants, reader.u16()?)?;
+    let count = reader.u16()?;
+    let mut elements = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let name = utf8_at(constants, reader.u16()?)?;
+        elements.push((name, read_element_value(reader, constants)?));
+    }
+    Ok(Annotation {
+        type_name,
+        elements,
+    })
+}
+
+impl ClassFile {
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        let mut reader = ByteReader::new(bytes);
+        if reader.u32()? != CLASS_MAGIC {
+            return Err(anyhow!("Not a class file."));
+        }
+        // Minor and major version.
+        reader.take(4)?;
+        let constants = read_constants(&mut reader)?;
+        let access_flags = reader.u16()?;
+        let this_class = reader.u16()?;
+        let _super_class = reader.u16()?;
+        let interface_count = reader.u16()? as usize;
+        reader.take(interface_count * 2)?;
+        let fields = read_members(&mut reader, &constants)?;
+        let methods = read_members(&mut reader, &constants)?;
+        let attributes = read_attributes(&mut reader, &constants)?;
+        Ok(ClassFile {
+            constants,
+            access_flags,
+            this_class,
+            fields,
+            methods,
+            attributes,
+        })
+    }
+
+    pub fn utf8(&self, idx: u16) -> Option<&str> {
+        match self.constants.get(idx as usize) {
+            Some(Constant::Utf8(s)) => Some(s.as_str()),
+            _ => None,
+        }
+    }
+
+    pub fn integer(&self, idx: u16) -> Option<i32> {
+        match self.constants.get(idx as usize) {
+            Some(Constant::Integer(i)) => Some(*i),
+            _ => None,
+        }
+    }
+
+    /// Resolves a CONSTANT_Class, CONSTANT_Module or CONSTANT_Package entry to its name, which
+    /// uses '/' as the package separator.
+    pub fn name_of(&self, idx: u16) -> Option<&str> {
+        match self.constants.get(idx as usize) {
+            Some(Constant::Class(name_idx))
+            | Some(Constant::Module(name_idx))
+            | Some(Constant::Package(name_idx)) => self.utf8(*name_idx),
+            _ => None,
+        }
+    }
+
+    pub fn this_class_name(&self) -> Option<&str> {
+        self.name_of(self.this_class)
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&Attribute> {
+        self.attributes.iter().find(|a| a.name == name)
+    }
+
+    /// Reads the class's runtime-retained annotations.
+    pub fn runtime_visible_annotations(&self) -> Result<Vec<Annotation>> {
+        let attribute = match self.attribute("RuntimeVisibleAnnotations") {
+            Some(attribute) => attribute,
+            None => return Ok(Vec::new()),
+        };
+        let mut reader = ByteReader::new(&attribute.data);
+        let count = reader.u16()?;
+        let mut annotations = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            annotations.push(read_annotation(&mut reader, &self.constants)?);
+        }
+        Ok(annotations)
+    }
+
+    /// The class name without its package.
+    pub fn simple_name(&self) -> Option<&str> {
+        self.this_class_name()
+            .map(|name| name.rsplit_once('/').map(|(_, n)| n).unwrap_or(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use super::*;
+
+    fn fixture(name: &str) -> Vec<u8> {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/classes")
+            .join(name);
+        fs::read(path).unwrap()
+    }
+
+    #[test]
+    fn parse_reads_names_members_and_annotations() {
+        let class_file = ClassFile::parse(&fixture("Widget.class")).unwrap();
+        assert_eq!(class_file.this_class_name(), Some("com/example/api/Widget"));
+        assert_eq!(class_file.simple_name(), Some("Widget"));
+        assert_ne!(class_file.access_flags & ACC_PUBLIC, 0);
+
+        // The long and double constants take two pool slots each, which the field names would
+        // be misread without.
+        let fields: Vec<&str> = class_file.fields.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(fields, vec!["SIZE", "RATIO"]);
+        let methods: Vec<&str> = class_file.methods.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(methods, vec!["<init>", "name"]);
+
+        let annotations = class_file.runtime_visible_annotations().unwrap();
+        assert_eq!(annotations.len(), 1);
+        assert_eq!(annotations[0].type_name, "Ljava/lang/Deprecated;");
+        match annotations[0].element("since") {
+            Some(ElementValue::Const(b's', idx)) => assert_eq!(class_file.utf8(*idx), Some("2")),
+            other => panic!("Unexpected since value: {:?}", other),
+        }
+        assert!(matches!(
+            annotations[0].element("forRemoval"),
+            Some(ElementValue::Const(b'Z', _))
+        ));
+    }
+
+    #[test]
+    fn parse_rejects_truncated_input() {
+        let bytes = fixture("Widget.class");
+        for len in 0..bytes.len() {
+            assert!(ClassFile::parse(&bytes[..len]).is_err(), "{} bytes", len);
+        }
+    }
+
+    #[test]
+    fn parse_rejects_garbage() {
+        assert!(ClassFile::parse(b"PK\x03\x04 not a class").is_err());
+
+        // A valid header followed by noise: unknown tags and out-of-range indexes must surface
+        // as errors rather than panics.
+        let mut seed: u32 = 0x1234_5678;
+        for _ in 0..200 {
+            let mut bytes = vec![0xca, 0xfe, 0xba, 0xbe, 0, 0, 0, 55];
+            for _ in 0..64 {
+                seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                bytes.push((seed >> 16) as u8);
+            }
+            let _ = ClassFile::parse(&bytes);
+        }
+
+        // A this_class index past the end of the constant pool names nothing.
+        let class_file = ClassFile::parse(&fixture("Widget.class")).unwrap();
+        let bad = ClassFile {
+            this_class: u16::MAX,
+            ..class_file
+        };
+        assert_eq!(bad.this_class_name(), None);
+    }
+}
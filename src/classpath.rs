@@ -0,0 +1,219 @@
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use zip::read::ZipArchive;
+
+use crate::manifest::Manifest;
+
+pub fn is_jar_name(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.eq_ignore_ascii_case("jar"))
+        .unwrap_or(false)
+}
+
+/// Expands a `dir/*` element the way the `java` launcher does: every `.jar` file, in any case,
+/// directly inside the directory, without recursing. Only a final component of exactly `*` is a
+/// wildcard, so `lib/*.jar` is taken literally.
+fn expand_wildcard(element: &str) -> Option<Vec<PathBuf>> {
+    let dir = if element == "*" {
+        Path::new(".")
+    } else {
+        Path::new(element.strip_suffix("/*")?)
+    };
+
+    let mut jars = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| p.is_file() && is_jar_name(p))
+                .collect::<Vec<PathBuf>>()
+        })
+        .unwrap_or_default();
+    // The launcher leaves the order unspecified. Sorting at least makes reindexing repeatable.
+    jars.sort();
+    Some(jars)
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded: Vec<u8> = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+    while idx < bytes.len() {
+        let hex = s.get(idx + 1..idx + 3).filter(|_| bytes[idx] == b'%');
+        match hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+            Some(b) => {
+                decoded.push(b);
+                idx += 3;
+            }
+            None => {
+                decoded.push(bytes[idx]);
+                idx += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Reads the `Class-Path` attribute of a jar's manifest. Its entries are space-separated URLs
+/// relative to the directory holding the jar.
+fn manifest_class_path(jar_path: &Path) -> Vec<PathBuf> {
+    let manifest = fs::File::open(jar_path)
+        .ok()
+        .and_then(|inf| ZipArchive::new(inf).ok())
+        .and_then(|mut archive| Manifest::from_zip(&mut archive));
+    let class_path = match manifest.as_ref().and_then(|m| m.get("Class-Path")) {
+        Some(class_path) => class_path.to_string(),
+        None => return Vec::new(),
+    };
+
+    let base_dir = jar_path.parent().unwrap_or_else(|| Path::new("."));
+    class_path
+        .split_whitespace()
+        .filter_map(|url| {
+            if let Some(local) = url.strip_prefix("file:") {
+                Some(PathBuf::from(percent_decode(local)))
+            } else if url.contains("://") {
+                // Remote entries cannot be indexed.
+                None
+            } else {
+                Some(base_dir.join(percent_decode(url)))
+            }
+        })
+        .collect()
+}
+
+fn expand_element(path: PathBuf, seen: &mut HashSet<PathBuf>, accum: &mut Vec<PathBuf>) {
+    // Manifests can reference each other, or the jar itself, so every element is visited once.
+    let key = fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+    if !seen.insert(key) {
+        return;
+    }
+
+    let is_jar = path.is_file() && is_jar_name(&path);
+    accum.push(path.clone());
+    if is_jar {
+        // The launcher silently skips manifest references that don't exist, and so do we.
+        for referenced in manifest_class_path(&path)
+            .into_iter()
+            .filter(|p| p.exists())
+        {
+            expand_element(referenced, seen, accum);
+        }
+    }
+}
+
+/// Turns a classpath string into the list of elements the `java` launcher would search:
+/// wildcards are expanded and manifest `Class-Path` references are followed recursively, each
+/// referenced element placed right after the jar that references it.
+pub fn expand_classpath(class_path: &str) -> Vec<PathBuf> {
+    let mut seen: HashSet<PathBuf> = HashSet::new();
+    let mut accum: Vec<PathBuf> = Vec::new();
+    for element in class_path.split(':').filter(|e| !e.is_empty()) {
+        match expand_wildcard(element) {
+            Some(jars) => {
+                for jar in jars {
+                    expand_element(jar, &mut seen, &mut accum);
+                }
+            }
+            None => expand_element(PathBuf::from(element), &mut seen, &mut accum),
+        }
+    }
+    accum
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use zip::write::{FileOptions, ZipWriter};
+
+    use super::*;
+
+    fn write_jar(path: &Path, class_path: Option<&str>) {
+        let mut jar = ZipWriter::new(fs::File::create(path).unwrap());
+        if let Some(class_path) = class_path {
+            jar.start_file("META-INF/MANIFEST.MF", FileOptions::default())
+                .unwrap();
+            write!(
+                jar,
+                "Manifest-Version: 1.0\r\nClass-Path: {}\r\n\r\n",
+                class_path
+            )
+            .unwrap();
+        }
+        jar.finish().unwrap();
+    }
+
+    #[test]
+    fn wildcards_take_jars_directly_inside_their_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["b.jar", "A.JAR", "notes.txt"] {
+            fs::write(dir.path().join(name), b"").unwrap();
+        }
+        fs::create_dir(dir.path().join("nested")).unwrap();
+        fs::write(dir.path().join("nested").join("c.jar"), b"").unwrap();
+
+        let wildcard = format!("{}/*", dir.path().display());
+        assert_eq!(
+            expand_wildcard(&wildcard),
+            Some(vec![dir.path().join("A.JAR"), dir.path().join("b.jar")])
+        );
+        assert_eq!(
+            expand_wildcard(&format!("{}/*.jar", dir.path().display())),
+            None
+        );
+        assert_eq!(expand_wildcard("lib/classes"), None);
+    }
+
+    #[test]
+    fn percent_decode_reads_escapes_and_keeps_stray_percents() {
+        assert_eq!(percent_decode("my%20lib.jar"), "my lib.jar");
+        assert_eq!(percent_decode("%E2%82%AC.jar"), "\u{20ac}.jar");
+        assert_eq!(percent_decode("100%.jar"), "100%.jar");
+        assert_eq!(percent_decode("%zz%2"), "%zz%2");
+    }
+
+    #[test]
+    fn manifest_class_path_is_relative_to_the_jar() {
+        let dir = tempfile::tempdir().unwrap();
+        let jar = dir.path().join("app.jar");
+        write_jar(
+            &jar,
+            Some("lib/my%20dep.jar file:/opt/shared.jar https://example.com/remote.jar"),
+        );
+        assert_eq!(
+            manifest_class_path(&jar),
+            vec![
+                dir.path().join("lib/my dep.jar"),
+                PathBuf::from("/opt/shared.jar"),
+            ]
+        );
+    }
+
+    #[test]
+    fn manifest_references_are_followed_once() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("lib")).unwrap();
+        // a.jar and b.jar reference each other, b.jar by a path that leaves and re-enters lib/.
+        write_jar(
+            &dir.path().join("a.jar"),
+            Some("lib/../lib/b.jar missing.jar"),
+        );
+        write_jar(&dir.path().join("lib/b.jar"), Some("../a.jar ../lib/b.jar"));
+
+        let expanded = expand_classpath(&format!("{}/a.jar", dir.path().display()));
+        assert_eq!(
+            expanded,
+            vec![
+                dir.path().join("a.jar"),
+                dir.path().join("lib/../lib/b.jar")
+            ]
+        );
+    }
+}
@@ -3,6 +3,8 @@
 
 use clap::{Args, Parser, Subcommand, ValueEnum};
 
+use crate::indexes::Scope;
+
 #[derive(Clone, Debug, Parser)]
 pub struct CmdLineArgs {
     #[command(subcommand)]
@@ -15,6 +17,16 @@ pub enum Commands {
     ClsQuery {
         index_name: String,
         class_name: String,
+        /// Also list packages their JPMS module does not export.
+        #[arg(long)]
+        include_unexported: bool,
+        /// Only list project types visible from this file's build module, and types visible
+        /// from the scope of its source root.
+        #[arg(long)]
+        file_path: Option<String>,
+        /// Only list types visible from code in this scope: main, test, compile or runtime.
+        #[arg(long)]
+        scope: Option<Scope>,
     },
 
     #[command(name = "pkgenum", arg_required_else_help = true)]
@@ -23,11 +35,37 @@ pub enum Commands {
         package_name: String,
     },
 
+    #[command(name = "srcquery", arg_required_else_help = true)]
+    SrcQuery {
+        index_name: String,
+        class_name: String,
+    },
+
+    #[command(name = "coordquery", arg_required_else_help = true)]
+    CoordQuery {
+        index_name: String,
+        class_name: String,
+    },
+
     #[command(name = "dropindex", arg_required_else_help = true)]
     DropIndex {
         index_name: String,
     },
 
+    /// Show or change the settings of an index. They apply to later reindexing.
+    #[command(arg_required_else_help = true)]
+    Settings {
+        index_name: String,
+        /// Leave out multi-release jar entries for Java releases newer than this.
+        #[arg(long, conflicts_with = "clear_target_release")]
+        target_release: Option<u32>,
+        #[arg(long)]
+        clear_target_release: bool,
+        /// Name Kotlin and Scala classes as their source does, reading every class file.
+        #[arg(long)]
+        jvm_lang_names: Option<bool>,
+    },
+
     #[command(arg_required_else_help = true)]
     Reindex {
         #[command(subcommand)]
@@ -51,6 +89,9 @@ pub enum ReindexCommands {
     Classpath {
         index_name: String,
         classpath_expr: String,
+        /// Tag the classes with the dependency scope of the classpath: compile, runtime or test.
+        #[arg(long)]
+        scope: Option<Scope>,
     },
 
     #[command(arg_required_else_help = true)]
@@ -62,6 +103,62 @@ pub enum ReindexCommands {
         image_file: String,
     },
 
+    /// Discover installed JDKs and index one as jdk<N>: the given major version, else the one in
+    /// $JAVA_HOME, else the newest.
+    Jdk {
+        major_version: Option<u32>,
+        /// Index every JDK found, each under its own jdk<N> name.
+        #[arg(long, conflicts_with = "major_version")]
+        all: bool,
+    },
+
+    /// Index a .jmod file or a directory of them, such as $JAVA_HOME/jmods.
+    #[command(arg_required_else_help = true)]
+    Jmod {
+        index_name: String,
+        jmod_path: String,
+    },
+
+    /// Index the API of a Java release from a JDK's lib/ct.sym, as used by javac --release.
+    #[command(name = "ct-sym", arg_required_else_help = true)]
+    CtSym {
+        index_name: String,
+        release: u32,
+        /// The JDK whose ct.sym to read. Defaults to $JAVA_HOME.
+        #[arg(long)]
+        java_home: Option<String>,
+    },
+
+    #[command(name = "jdk-src", arg_required_else_help = true)]
+    JdkSrc { index_name: String, src_zip: String },
+
+    /// Index the jars of a local Maven repository, defaulting to ~/.m2/repository.
+    #[command(arg_required_else_help = true)]
+    M2 {
+        index_name: String,
+        repo_dir: Option<String>,
+    },
+
+    /// Index Gradle's module cache, defaulting to ~/.gradle/caches/modules-2/files-2.1.
+    #[command(name = "gradle-cache", arg_required_else_help = true)]
+    GradleCache {
+        index_name: String,
+        cache_dir: Option<String>,
+    },
+
+    /// Resolve a pom.xml's dependencies from the local Maven repository and index them.
+    #[command(arg_required_else_help = true)]
+    Pom {
+        index_name: String,
+        pom_file: String,
+        #[arg(long)]
+        repo_dir: Option<String>,
+    },
+
     #[command(arg_required_else_help = true)]
     Project { index_name: String, src_dir: String },
+
+    /// Reindex the classpath and sources declared by the .cpid.toml in or above a path.
+    #[command(arg_required_else_help = true)]
+    Configured { path: String },
 }
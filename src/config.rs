@@ -0,0 +1,211 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use serde_derive::Deserialize;
+
+pub const PROJECT_CONFIG_FILE: &str = ".cpid.toml";
+
+/// Settings a project keeps in a `.cpid.toml` at its root.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProjectConfig {
+    pub indexes: IndexesConfig,
+    pub classpath: ClasspathConfig,
+    pub crawl: CrawlConfig,
+}
+
+/// Names the indexes that together answer queries made from the project.
+///
+/// ```toml
+/// [indexes]
+/// sources = "myapp"
+/// dependencies = "myapp-deps"
+/// jdk = "jdk17"
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct IndexesConfig {
+    /// The index the project's sources are crawled into. Defaults to the project directory's
+    /// name.
+    pub sources: Option<String>,
+    /// The index the classpath is read into. Defaults to the sources index's name with a
+    /// `-deps` suffix. It must differ from the sources index, where a type leaves the index with
+    /// the last source file declaring it.
+    pub dependencies: Option<String>,
+    /// An index of the JDK the project builds with, such as one made by `cpid reindex jdk`.
+    pub jdk: Option<String>,
+}
+
+/// Where the project's dependencies come from. Paths are relative to the project root, and
+/// classpath entries may end in `/*` as on a `java` command line.
+///
+/// ```toml
+/// [classpath]
+/// pom = "pom.xml"
+/// compile = ["lib/*"]
+/// test = ["test-lib/*"]
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClasspathConfig {
+    /// A pom.xml whose dependencies are resolved from the local Maven repository.
+    pub pom: Option<String>,
+    pub compile: Vec<String>,
+    pub runtime: Vec<String>,
+    pub test: Vec<String>,
+}
+
+/// Narrows which sources a project crawl reads. Globs and roots are relative to the project root.
+///
+/// ```toml
+/// [crawl]
+/// include = ["src/**"]
+/// exclude = ["**/legacy/**"]
+/// generated_roots = ["target/generated-sources/annotations"]
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CrawlConfig {
+    /// When given, only files matching one of these are crawled.
+    pub include: Vec<String>,
+    /// Files matching any of these are never crawled.
+    pub exclude: Vec<String>,
+    /// Directories of generated sources to crawl even though they sit in build output, in a
+    /// directory named `generated`, or in ignored paths.
+    pub generated_roots: Vec<String>,
+}
+
+impl ProjectConfig {
+    pub fn parse(text: &str) -> Result<Self> {
+        Ok(toml::from_str(text)?)
+    }
+
+    /// Reads the config of the project rooted at `project_root`, defaulting when it has none.
+    pub fn load(project_root: &Path) -> Result<Self> {
+        let config_path = project_root.join(PROJECT_CONFIG_FILE);
+        match fs::read_to_string(&config_path) {
+            Ok(text) => ProjectConfig::parse(&text)
+                .map_err(|e| anyhow!("Invalid {}: {}", config_path.display(), e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(ProjectConfig::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Finds the `.cpid.toml` nearest above `path`, which may name a file or a directory.
+    pub fn discover(path: &Path) -> Result<Option<ConfiguredProject>> {
+        let start = match fs::canonicalize(path) {
+            Ok(path) => path,
+            Err(_) => env::current_dir()?.join(path),
+        };
+        for dir in start.ancestors() {
+            if dir.join(PROJECT_CONFIG_FILE).is_file() {
+                return Ok(Some(ConfiguredProject {
+                    root: dir.to_path_buf(),
+                    config: ProjectConfig::load(dir)?,
+                }));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// A project found through its `.cpid.toml`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfiguredProject {
+    /// The directory holding the `.cpid.toml`.
+    pub root: PathBuf,
+    pub config: ProjectConfig,
+}
+
+impl ConfiguredProject {
+    /// Like `ProjectConfig::discover`, but failing when no config is found.
+    pub fn discover(path: &Path) -> Result<Self> {
+        ProjectConfig::discover(path)?.ok_or_else(|| {
+            anyhow!(
+                "No {} found in {} or above it.",
+                PROJECT_CONFIG_FILE,
+                path.display()
+            )
+        })
+    }
+
+    pub fn sources_index(&self) -> String {
+        self.config.indexes.sources.clone().unwrap_or_else(|| {
+            self.root
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        })
+    }
+
+    pub fn dependencies_index(&self) -> String {
+        self.config
+            .indexes
+            .dependencies
+            .clone()
+            .unwrap_or_else(|| format!("{}-deps", self.sources_index()))
+    }
+
+    /// Lists the indexes a query from the project consults: sources, dependencies, then the JDK.
+    pub fn query_indexes(&self) -> Vec<String> {
+        let mut names: Vec<String> = vec![self.sources_index(), self.dependencies_index()];
+        names.extend(self.config.indexes.jdk.clone());
+        names.dedup();
+        names
+    }
+
+    /// Joins classpath entries relative to the project root into a classpath expression.
+    pub fn classpath_expr(&self, entries: &[String]) -> String {
+        entries
+            .iter()
+            .map(|entry| self.root.join(entry).to_string_lossy().into_owned())
+            .collect::<Vec<String>>()
+            .join(":")
+    }
+}
+
+fn glob_set(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(Glob::new(pattern)?);
+    }
+    Ok(builder.build()?)
+}
+
+/// A `CrawlConfig` with its globs compiled.
+pub struct CrawlFilter {
+    include: Option<GlobSet>,
+    exclude: GlobSet,
+}
+
+impl CrawlFilter {
+    pub fn new(config: &CrawlConfig) -> Result<Self> {
+        let include = if config.include.is_empty() {
+            None
+        } else {
+            Some(glob_set(&config.include)?)
+        };
+        Ok(CrawlFilter {
+            include,
+            exclude: glob_set(&config.exclude)?,
+        })
+    }
+
+    /// Tests a file path relative to the project root against the include and exclude globs.
+    pub fn accepts(&self, rel_path: &Path) -> bool {
+        let included = self
+            .include
+            .as_ref()
+            .map(|include| include.is_match(rel_path))
+            .unwrap_or(true);
+        included && !self.excludes(rel_path)
+    }
+
+    pub fn excludes(&self, rel_path: &Path) -> bool {
+        self.exclude.is_match(rel_path)
+    }
+}
@@ -0,0 +1,114 @@
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+
+use std::path::{Path, PathBuf};
+
+use crate::maven::Coordinate;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CacheJarKind {
+    Classes,
+    Sources,
+    Javadoc,
+}
+
+pub fn default_cache_dir() -> Option<PathBuf> {
+    let gradle_home = std::env::var_os("GRADLE_USER_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".gradle")))?;
+    Some(
+        gradle_home
+            .join("caches")
+            .join("modules-2")
+            .join("files-2.1"),
+    )
+}
+
+/// Identifies a jar in Gradle's module cache, which is laid out as
+/// `<groupId>/<artifactId>/<version>/<sha1>/<artifactId>-<version>[-classifier].jar`. Unlike a
+/// Maven repository the group is a single dotted directory name.
+pub fn classify_cache_jar(
+    cache_root: &Path,
+    jar_path: &Path,
+) -> Option<(Coordinate, CacheJarKind)> {
+    let rel = jar_path.strip_prefix(cache_root).ok()?;
+    let parts = rel
+        .iter()
+        .map(|p| p.to_str())
+        .collect::<Option<Vec<&str>>>()?;
+    let (group_id, artifact_id, version, file_name) = match parts[..] {
+        [g, a, v, _hash, f] => (g, a, v, f),
+        _ => return None,
+    };
+
+    let stub = file_name
+        .strip_prefix(&format!("{}-{}", artifact_id, version))?
+        .strip_suffix(".jar")?;
+    let kind = match stub {
+        "" => CacheJarKind::Classes,
+        "-sources" => CacheJarKind::Sources,
+        "-javadoc" => CacheJarKind::Javadoc,
+        _ => return None,
+    };
+    Some((Coordinate::new(group_id, artifact_id, version), kind))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn guava_jar(cache: &Path, file_name: &str) -> PathBuf {
+        cache
+            .join("com.google.guava/guava/31.1-jre/f4c3e3bbd1d4e0b0d5e2c9b1a2f3c4d5e6f7a8b9")
+            .join(file_name)
+    }
+
+    #[test]
+    fn classify_cache_jar_reads_the_cache_layout() {
+        let cache = Path::new("/home/user/.gradle/caches/modules-2/files-2.1");
+        let guava = Coordinate::new("com.google.guava", "guava", "31.1-jre");
+        assert_eq!(
+            classify_cache_jar(cache, &guava_jar(cache, "guava-31.1-jre.jar")),
+            Some((guava.clone(), CacheJarKind::Classes))
+        );
+        assert_eq!(
+            classify_cache_jar(cache, &guava_jar(cache, "guava-31.1-jre-sources.jar")),
+            Some((guava.clone(), CacheJarKind::Sources))
+        );
+        assert_eq!(
+            classify_cache_jar(cache, &guava_jar(cache, "guava-31.1-jre-javadoc.jar")),
+            Some((guava, CacheJarKind::Javadoc))
+        );
+    }
+
+    #[test]
+    fn classify_cache_jar_rejects_other_files() {
+        let cache = Path::new("/home/user/.gradle/caches/modules-2/files-2.1");
+        for file_name in [
+            "guava-31.1-jre.pom",
+            "guava-31.1-jre-tests.jar",
+            "failureaccess-1.0.1.jar",
+        ] {
+            assert_eq!(
+                classify_cache_jar(cache, &guava_jar(cache, file_name)),
+                None,
+                "{}",
+                file_name
+            );
+        }
+        assert_eq!(
+            classify_cache_jar(
+                cache,
+                &cache.join("com.google.guava/guava/31.1-jre/guava-31.1-jre.jar")
+            ),
+            None
+        );
+        assert_eq!(
+            classify_cache_jar(
+                cache,
+                Path::new("/opt/lib/com.google.guava/guava/31.1-jre/abc/guava-31.1-jre.jar")
+            ),
+            None
+        );
+    }
+}
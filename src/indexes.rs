@@ -1,28 +1,111 @@
 #![allow(unused_imports)]
 #![allow(unused_variables)]
 
-use std::collections::HashMap;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 use std::convert::identity;
 use std::fs;
 use std::io;
-use std::io::{BufRead, BufReader};
-use std::path::Path;
+use std::io::{BufRead, BufReader, Cursor, Read, Seek};
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
+use std::str::FromStr;
+use std::time::UNIX_EPOCH;
 
 use anyhow::{anyhow, bail, Result};
 use regex::Regex;
+use serde_derive::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use walkdir::WalkDir;
 use zip::read::ZipArchive;
 use zip::result::ZipResult;
 
 extern crate sled;
 
+use crate::classfile::ClassFile;
+use crate::classpath::is_jar_name;
+use crate::config::{ConfiguredProject, ProjectConfig};
+use crate::gradle;
+use crate::gradle::CacheJarKind;
+use crate::jdk::{ct_sym_release_char, jimage_command_for, JdkInstall, JMOD_HEADER};
+use crate::jpms::{ModuleDescriptor, MODULE_INFO_CLASS};
+use crate::jvmlang;
+use crate::manifest::Manifest;
+use crate::maven;
+use crate::maven::Coordinate;
+use crate::multimodule;
+use crate::multimodule::BuildModule;
+use crate::pool;
+use crate::project;
+use crate::project::SourceParser;
+
 const CLASS_PACKAGES_TREE_SUFFIX: &str = "-class_pkgs";
 const PACKAGE_CONTENTS_TREE_SUFFIX: &str = "-pkg_classes";
+const CLASS_SOURCES_TREE_SUFFIX: &str = "-class_srcs";
+const CLASS_COORDINATES_TREE_SUFFIX: &str = "-class_coords";
+const SETTINGS_TREE_SUFFIX: &str = "-settings";
+const PACKAGE_MODULES_TREE_SUFFIX: &str = "-pkg_modules";
+const PROJECT_FILES_TREE_SUFFIX: &str = "-project_files";
+const BUILD_MODULES_TREE_SUFFIX: &str = "-build_modules";
+const CLASS_BUILD_MODULES_TREE_SUFFIX: &str = "-class_build_modules";
+const CLASS_SCOPES_TREE_SUFFIX: &str = "-class_scopes";
+const CLASS_PROJECT_FILES_TREE_SUFFIX: &str = "-class_project_files";
+const PROJECT_ROOTS_TREE_SUFFIX: &str = "-project_roots";
+
+const SETTINGS_KEY: &str = "settings";
+const VERSIONED_ENTRY_PREFIX: &str = "META-INF/versions/";
+
+// Compared against lowercased file names, so `.JAR` and `.WAR` archives are found too.
+const ARCHIVE_SUFFIXES: &[&str] = &[".jar", ".war", ".aar"];
+
+// Android libraries ship their classes as jars inside the AAR: classes.jar, plus any bundled
+// dependencies under libs/.
+const AAR_MARKER_ENTRY: &str = "AndroidManifest.xml";
+const AAR_CLASSES_ENTRY: &str = "classes.jar";
+const AAR_LIBS_PREFIX: &str = "libs/";
+
+// Executable Spring Boot jars and WARs keep the application's classes under a prefix and its
+// dependencies as nested jars. Each layout is (classes prefix, nested library prefix).
+const NESTED_LAYOUTS: &[(&str, &str)] = &[
+    ("BOOT-INF/classes/", "BOOT-INF/lib/"),
+    ("WEB-INF/classes/", "WEB-INF/lib/"),
+];
+
+const TREE_SUFFIXES: &[&str] = &[
+    CLASS_PACKAGES_TREE_SUFFIX,
+    PACKAGE_CONTENTS_TREE_SUFFIX,
+    CLASS_SOURCES_TREE_SUFFIX,
+    CLASS_COORDINATES_TREE_SUFFIX,
+    SETTINGS_TREE_SUFFIX,
+    PACKAGE_MODULES_TREE_SUFFIX,
+    PROJECT_FILES_TREE_SUFFIX,
+    BUILD_MODULES_TREE_SUFFIX,
+    CLASS_BUILD_MODULES_TREE_SUFFIX,
+    CLASS_SCOPES_TREE_SUFFIX,
+    CLASS_PROJECT_FILES_TREE_SUFFIX,
+    PROJECT_ROOTS_TREE_SUFFIX,
+];
 
 fn tree_name(index_name: &str, suffix: &str) -> String {
     format!("{}{}", index_name, suffix)
 }
 
+pub fn remove_string_from_list(old_entry: &str, old_bytes: Option<&[u8]>) -> Option<Vec<u8>> {
+    let mut list: Vec<String> = old_bytes
+        .and_then(|b| serde_json::from_slice(b).ok())
+        .unwrap_or_default();
+
+    list.retain(|entry| entry != old_entry);
+    if list.is_empty() {
+        return None;
+    }
+
+    serde_json::to_string(&list)
+        .map(|b| b.into_bytes())
+        .map_err(|e| eprintln!("WTF?! {}", e))
+        .ok()
+}
+
 pub fn merge_string_into_list(new_entry: &str, old_bytes: Option<&[u8]>) -> Option<Vec<u8>> {
     let mut list: Vec<String> = old_bytes
         .and_then(|b| serde_json::from_slice(b).ok())
@@ -38,14 +121,220 @@ pub fn merge_string_into_list(new_entry: &str, old_bytes: Option<&[u8]>) -> Opti
         .ok()
 }
 
+/// Where the source for a fully qualified class name can be read from. The entry is relative to
+/// the archive, which is the form vim's zip plugin expects (`zipfile:<archive>::<entry>`).
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct SourceLocation {
+    pub archive: String,
+    pub entry: String,
+}
+
+/// Options chosen per index that change how archives are read into it.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct IndexSettings {
+    /// The Java release the index's users compile for. Multi-release jar entries for newer
+    /// releases are left out.
+    #[serde(default)]
+    pub target_release: Option<u32>,
+    /// Read each class file to name Kotlin and Scala classes as their source does. This costs a
+    /// full read of every archive, so it is off unless asked for.
+    #[serde(default)]
+    pub jvm_lang_names: bool,
+}
+
+/// The JPMS module a package belongs to. Code in another module can only import the package's
+/// types when the module exports it, and then only after a matching `requires`.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct PackageModule {
+    pub module: String,
+    pub exported: bool,
+}
+
+/// What a project source file contributed to the index when it was last crawled, so that a
+/// reindex can skip it while it is unchanged and take back its types once it changes.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct ProjectFile {
+    /// Modification time in nanoseconds since the epoch.
+    pub mtime: u64,
+    /// Hex SHA-256 of the contents.
+    pub hash: String,
+    pub package_name: String,
+    pub types: Vec<String>,
+    /// The directory of the build module holding the file, in a multi-module build.
+    #[serde(default)]
+    pub build_module: Option<String>,
+}
+
+/// Which classpath a type is on. Project sources are `Main` or `Test`; dependency jars are
+/// `Compile`, `Runtime` or `Test` as their build declares them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    Main,
+    Test,
+    Compile,
+    Runtime,
+}
+
+impl Scope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::Main => "main",
+            Scope::Test => "test",
+            Scope::Compile => "compile",
+            Scope::Runtime => "runtime",
+        }
+    }
+
+    /// Maps a resolved Maven dependency scope. Provided and system dependencies are on the
+    /// compile classpath like compile ones.
+    pub fn from_maven(maven_scope: &str) -> Scope {
+        match maven_scope {
+            "runtime" => Scope::Runtime,
+            "test" => Scope::Test,
+            _ => Scope::Compile,
+        }
+    }
+
+    /// Tests whether code in `query_scope` can use types tagged with this scope. Main code
+    /// compiles against main sources and compile dependencies only; tests see everything.
+    pub fn visible_from(&self, query_scope: Scope) -> bool {
+        match query_scope {
+            Scope::Main | Scope::Compile => matches!(self, Scope::Main | Scope::Compile),
+            Scope::Runtime => *self != Scope::Test,
+            Scope::Test => true,
+        }
+    }
+}
+
+impl FromStr for Scope {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        match name {
+            "main" => Ok(Scope::Main),
+            "test" => Ok(Scope::Test),
+            "compile" => Ok(Scope::Compile),
+            "runtime" => Ok(Scope::Runtime),
+            _ => Err(anyhow!(
+                "Unknown scope {}: expected main, test, compile or runtime.",
+                name
+            )),
+        }
+    }
+}
+
+impl ProjectFile {
+    fn class_pairs(&self) -> BTreeSet<(String, String)> {
+        self.types
+            .iter()
+            .map(|t| (t.clone(), self.package_name.clone()))
+            .collect()
+    }
+}
+
+/// Narrows the packages a class query answers with.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct QueryFilter {
+    /// Also answer with packages their module does not export, such as `jdk.internal.misc`.
+    pub include_unexported: bool,
+    /// When set, project types are only answered with when declared in one of these build
+    /// modules. Types from archives are unaffected.
+    pub visible_build_modules: Option<HashSet<String>>,
+    /// When set, only types visible from code in this scope are answered with. Types indexed
+    /// without a scope are unaffected.
+    pub scope: Option<Scope>,
+}
+
+/// The scope each source of a type gives it, keyed by the archive, class directory or project
+/// file providing it. A source without a scope makes the type visible from everywhere.
+type ScopesBySource = BTreeMap<String, Option<Scope>>;
+
+/// Stored scopes, as written now or as the plain list of scopes earlier versions merged into.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredScopes {
+    BySource(ScopesBySource),
+    Merged(Vec<Scope>),
+}
+
+fn read_scopes(bytes: Option<&[u8]>) -> ScopesBySource {
+    match bytes.map(serde_json::from_slice::<StoredScopes>) {
+        Some(Ok(StoredScopes::BySource(scopes))) => scopes,
+        Some(Ok(StoredScopes::Merged(scopes))) => scopes
+            .into_iter()
+            .map(|scope| (scope.as_str().to_string(), Some(scope)))
+            .collect(),
+        _ => ScopesBySource::new(),
+    }
+}
+
+fn write_scopes(scopes: &ScopesBySource) -> Option<Vec<u8>> {
+    if scopes.is_empty() {
+        None
+    } else {
+        serde_json::to_vec(scopes).ok()
+    }
+}
+
+/// Tests whether any source of a type makes it visible from `query_scope`.
+fn scopes_visible_from(scopes: &ScopesBySource, query_scope: Scope) -> bool {
+    scopes.is_empty()
+        || scopes
+            .values()
+            .any(|scope| scope.map(|s| s.visible_from(query_scope)).unwrap_or(true))
+}
+
+/// Joins a package and class name, leaving the unnamed package out.
+fn qualified_class_name(package_name: &str, class_name: &str) -> String {
+    if package_name.is_empty() {
+        class_name.to_string()
+    } else {
+        format!("{}.{}", package_name, class_name)
+    }
+}
+
+/// How far a reindex has got through the archives it reads.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct ReindexProgress {
+    pub archives_done: usize,
+    pub archives_total: usize,
+    /// The archive most recently read.
+    pub current_archive: Option<String>,
+}
+
+/// Receives progress from a reindex as it goes.
+pub type ProgressFn = dyn Fn(&ReindexProgress) + Sync;
+
 pub struct Index<'a> {
     db: &'a sled::Db,
     index_name: &'a str,
+    progress: Option<&'a ProgressFn>,
 }
 
 impl<'a> Index<'a> {
     pub fn new(db: &'a sled::Db, index_name: &'a str) -> Self {
-        Index { db, index_name }
+        Index {
+            db,
+            index_name,
+            progress: None,
+        }
+    }
+
+    /// Has reindexing into this index report its progress to `progress`.
+    pub fn with_progress(mut self, progress: &'a ProgressFn) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    fn report_progress(&self, archives_done: usize, archives_total: usize, current: &Path) {
+        if let Some(progress) = self.progress {
+            progress(&ReindexProgress {
+                archives_done,
+                archives_total,
+                current_archive: Some(current.display().to_string()),
+            });
+        }
     }
 
     pub fn name(&self) -> &'a str {
@@ -64,11 +353,84 @@ impl<'a> Index<'a> {
             .expect("database tree")
     }
 
-    pub fn drop_trees(&self) -> Result<()> {
+    pub fn open_class_sources_tree(&self) -> sled::Tree {
+        self.db
+            .open_tree(tree_name(self.index_name, CLASS_SOURCES_TREE_SUFFIX))
+            .expect("database tree")
+    }
+
+    pub fn open_class_coordinates_tree(&self) -> sled::Tree {
+        self.db
+            .open_tree(tree_name(self.index_name, CLASS_COORDINATES_TREE_SUFFIX))
+            .expect("database tree")
+    }
+
+    pub fn open_settings_tree(&self) -> sled::Tree {
+        self.db
+            .open_tree(tree_name(self.index_name, SETTINGS_TREE_SUFFIX))
+            .expect("database tree")
+    }
+
+    pub fn open_package_modules_tree(&self) -> sled::Tree {
         self.db
-            .drop_tree(tree_name(self.index_name, CLASS_PACKAGES_TREE_SUFFIX))?;
+            .open_tree(tree_name(self.index_name, PACKAGE_MODULES_TREE_SUFFIX))
+            .expect("database tree")
+    }
+
+    pub fn open_project_files_tree(&self) -> sled::Tree {
+        self.db
+            .open_tree(tree_name(self.index_name, PROJECT_FILES_TREE_SUFFIX))
+            .expect("database tree")
+    }
+
+    pub fn open_build_modules_tree(&self) -> sled::Tree {
+        self.db
+            .open_tree(tree_name(self.index_name, BUILD_MODULES_TREE_SUFFIX))
+            .expect("database tree")
+    }
+
+    pub fn open_class_build_modules_tree(&self) -> sled::Tree {
+        self.db
+            .open_tree(tree_name(self.index_name, CLASS_BUILD_MODULES_TREE_SUFFIX))
+            .expect("database tree")
+    }
+
+    pub fn open_class_scopes_tree(&self) -> sled::Tree {
+        self.db
+            .open_tree(tree_name(self.index_name, CLASS_SCOPES_TREE_SUFFIX))
+            .expect("database tree")
+    }
+
+    pub fn open_class_project_files_tree(&self) -> sled::Tree {
+        self.db
+            .open_tree(tree_name(self.index_name, CLASS_PROJECT_FILES_TREE_SUFFIX))
+            .expect("database tree")
+    }
+
+    pub fn open_project_roots_tree(&self) -> sled::Tree {
         self.db
-            .drop_tree(tree_name(self.index_name, PACKAGE_CONTENTS_TREE_SUFFIX))?;
+            .open_tree(tree_name(self.index_name, PROJECT_ROOTS_TREE_SUFFIX))
+            .expect("database tree")
+    }
+
+    pub fn settings(&self) -> Result<IndexSettings> {
+        match self.open_settings_tree().get(SETTINGS_KEY)? {
+            None => Ok(IndexSettings::default()),
+            Some(val_bytes) => Ok(serde_json::from_slice(&val_bytes)?),
+        }
+    }
+
+    pub fn store_settings(&self, settings: &IndexSettings) -> Result<()> {
+        let settings_tree = self.open_settings_tree();
+        settings_tree.insert(SETTINGS_KEY, serde_json::to_vec(settings)?)?;
+        settings_tree.flush()?;
+        Ok(())
+    }
+
+    pub fn drop_trees(&self) -> Result<()> {
+        for suffix in TREE_SUFFIXES {
+            self.db.drop_tree(tree_name(self.index_name, suffix))?;
+        }
         Ok(())
     }
 
@@ -95,7 +457,368 @@ impl<'a> Index<'a> {
         Ok(())
     }
 
+    /// Takes `(class_name, package_name)` pairs back out of the index, dropping names and
+    /// packages left with nothing in them.
+    pub fn remove_class_pairs(&self, pairs: &[(String, String)]) -> Result<()> {
+        let class_packages_tree = self.open_class_packages_tree();
+        let package_contents_tree = self.open_package_contents_tree();
+
+        for (class_name, package_name) in pairs {
+            class_packages_tree
+                .update_and_fetch(class_name, |bytes: Option<&[u8]>| {
+                    remove_string_from_list(package_name, bytes)
+                })
+                .map_err(|e| anyhow!(e.to_string()))?;
+
+            package_contents_tree
+                .update_and_fetch(package_name, |bytes: Option<&[u8]>| {
+                    remove_string_from_list(class_name, bytes)
+                })
+                .map_err(|e| anyhow!(e.to_string()))?;
+        }
+
+        class_packages_tree.flush()?;
+        package_contents_tree.flush()?;
+        Ok(())
+    }
+
+    /// Replaces the build modules recorded for the project at `project_root`.
+    pub fn store_build_modules(&self, project_root: &Path, modules: &[BuildModule]) -> Result<()> {
+        let build_modules_tree = self.open_build_modules_tree();
+        for item in build_modules_tree.iter() {
+            let (key, _) = item?;
+            if Path::new(&*String::from_utf8_lossy(&key)).starts_with(project_root) {
+                build_modules_tree.remove(key)?;
+            }
+        }
+        for module in modules {
+            build_modules_tree.insert(
+                module.dir.to_string_lossy().as_bytes(),
+                serde_json::to_vec(module)?,
+            )?;
+        }
+        build_modules_tree.flush()?;
+        Ok(())
+    }
+
+    /// Finds the innermost project directory crawled into the index that holds `path`.
+    pub fn project_root_for(&self, path: &Path) -> Result<Option<PathBuf>> {
+        let mut innermost: Option<PathBuf> = None;
+        for item in self.open_project_roots_tree().iter() {
+            let (key, _) = item?;
+            let root = PathBuf::from(&*String::from_utf8_lossy(&key));
+            let is_inner = innermost
+                .as_ref()
+                .map(|found| root.starts_with(found))
+                .unwrap_or(true);
+            if path.starts_with(&root) && is_inner {
+                innermost = Some(root);
+            }
+        }
+        Ok(innermost)
+    }
+
+    pub fn build_modules(&self) -> Result<Vec<BuildModule>> {
+        let mut modules: Vec<BuildModule> = Vec::new();
+        for item in self.open_build_modules_tree().iter() {
+            let (_, val_bytes) = item?;
+            modules.push(serde_json::from_slice(&val_bytes)?);
+        }
+        Ok(modules)
+    }
+
+    /// Names the build modules whose types code in `file_path` can use: its own module and those
+    /// it depends on, directly or not. Files outside any build module see everything. The path is
+    /// canonicalized first, as the recorded module directories are.
+    pub fn visible_build_modules(&self, file_path: &Path) -> Result<Option<HashSet<String>>> {
+        let modules = self.build_modules()?;
+        let file_path = multimodule::canonical_path(file_path);
+        let own_module = match multimodule::module_for_path(&modules, &file_path) {
+            Some(module) => module,
+            None => return Ok(None),
+        };
+        let modules_by_dir: HashMap<&Path, &BuildModule> =
+            modules.iter().map(|m| (m.dir.as_path(), m)).collect();
+
+        let mut visible: HashSet<String> = HashSet::new();
+        let mut pending: VecDeque<&BuildModule> = VecDeque::from([own_module]);
+        while let Some(module) = pending.pop_front() {
+            if visible.insert(module.dir.to_string_lossy().into_owned()) {
+                pending.extend(
+                    module
+                        .dependencies
+                        .iter()
+                        .filter_map(|dep| modules_by_dir.get(dep.as_path())),
+                );
+            }
+        }
+        Ok(Some(visible))
+    }
+
+    /// Builds the filter for a query made from `file_path`, if given. Without an explicit
+    /// `scope`, the scope is that of the source root holding the file.
+    pub fn query_filter(
+        &self,
+        include_unexported: bool,
+        file_path: Option<&Path>,
+        scope: Option<Scope>,
+    ) -> Result<QueryFilter> {
+        let visible_build_modules = match file_path {
+            Some(file_path) => self.visible_build_modules(file_path)?,
+            None => None,
+        };
+        Ok(QueryFilter {
+            include_unexported,
+            visible_build_modules,
+            scope: scope.or_else(|| file_path.and_then(project::source_scope)),
+        })
+    }
+
+    /// Records which build modules declare each of the given project types, from the crawl
+    /// records of the files declaring them. Types any file outside a build module declares stay
+    /// unrecorded, and so visible everywhere.
+    fn update_class_build_modules(
+        &self,
+        declaring_files: &BTreeMap<(String, String), Vec<String>>,
+    ) -> Result<()> {
+        let class_build_modules_tree = self.open_class_build_modules_tree();
+        for ((class_name, package_name), paths) in declaring_files {
+            let mut modules: Option<BTreeSet<String>> = Some(BTreeSet::new());
+            for path in paths {
+                let build_module = self.project_file(path)?.and_then(|f| f.build_module);
+                match (modules.as_mut(), build_module) {
+                    (Some(modules_so_far), Some(module)) => {
+                        modules_so_far.insert(module);
+                    }
+                    _ => modules = None,
+                }
+            }
+
+            let key = qualified_class_name(package_name, class_name);
+            match modules {
+                Some(modules) if !modules.is_empty() => {
+                    class_build_modules_tree.insert(key, serde_json::to_vec(&modules)?)?;
+                }
+                _ => {
+                    class_build_modules_tree.remove(key)?;
+                }
+            }
+        }
+        class_build_modules_tree.flush()?;
+        Ok(())
+    }
+
+    /// Replaces the scopes project files give each of the given project types with those of the
+    /// files now declaring them. Scopes from archives providing the same names are kept.
+    fn update_class_scopes(
+        &self,
+        declaring_files: &BTreeMap<(String, String), Vec<String>>,
+    ) -> Result<()> {
+        let class_scopes_tree = self.open_class_scopes_tree();
+        for ((class_name, package_name), paths) in declaring_files {
+            class_scopes_tree
+                .update_and_fetch(
+                    qualified_class_name(package_name, class_name),
+                    |bytes: Option<&[u8]>| {
+                        let mut scopes = read_scopes(bytes);
+                        scopes.retain(|source, _| !project::is_source_file(Path::new(source)));
+                        for path in paths {
+                            let scope =
+                                project::source_scope(Path::new(path)).unwrap_or(Scope::Main);
+                            scopes.insert(path.clone(), Some(scope));
+                        }
+                        write_scopes(&scopes)
+                    },
+                )
+                .map_err(|e| anyhow!(e.to_string()))?;
+        }
+        class_scopes_tree.flush()?;
+        Ok(())
+    }
+
+    /// Loads the crawl records of every project file in the index, keyed by path.
+    pub fn project_files(&self) -> Result<BTreeMap<String, ProjectFile>> {
+        let mut files: BTreeMap<String, ProjectFile> = BTreeMap::new();
+        for item in self.open_project_files_tree().iter() {
+            let (key, val_bytes) = item?;
+            files.insert(
+                String::from_utf8_lossy(&key).into_owned(),
+                serde_json::from_slice(&val_bytes)?,
+            );
+        }
+        Ok(files)
+    }
+
+    /// Loads the crawl record of one project file.
+    pub fn project_file(&self, path: &str) -> Result<Option<ProjectFile>> {
+        Ok(self
+            .open_project_files_tree()
+            .get(path)?
+            .map(|val_bytes| serde_json::from_slice(&val_bytes))
+            .transpose()?)
+    }
+
+    /// Names the project files declaring a type.
+    fn declaring_files(&self, class_name: &str, package_name: &str) -> Result<Vec<String>> {
+        Ok(self
+            .open_class_project_files_tree()
+            .get(qualified_class_name(package_name, class_name))?
+            .map(|val_bytes| serde_json::from_slice(&val_bytes))
+            .transpose()?
+            .unwrap_or_default())
+    }
+
+    /// Applies changes to the crawled project files, `None` marking a deleted file. Each project
+    /// type keeps the list of files declaring it, so only the records of the changed files and
+    /// of the types they touch are read. A type leaves the index along with the last file
+    /// declaring it.
+    pub fn update_project_files(
+        &self,
+        changes: &BTreeMap<String, Option<ProjectFile>>,
+    ) -> Result<()> {
+        if changes.is_empty() {
+            return Ok(());
+        }
+        let project_files_tree = self.open_project_files_tree();
+        let class_project_files_tree = self.open_class_project_files_tree();
+        let mut affected_pairs: BTreeSet<(String, String)> = BTreeSet::new();
+        for (path, change) in changes {
+            let stale_pairs = self
+                .project_file(path)?
+                .map(|f| f.class_pairs())
+                .unwrap_or_default();
+            let new_pairs = change.as_ref().map(|f| f.class_pairs()).unwrap_or_default();
+            for (class_name, package_name) in stale_pairs.difference(&new_pairs) {
+                class_project_files_tree
+                    .update_and_fetch(
+                        qualified_class_name(package_name, class_name),
+                        |bytes: Option<&[u8]>| remove_string_from_list(path, bytes),
+                    )
+                    .map_err(|e| anyhow!(e.to_string()))?;
+            }
+            for (class_name, package_name) in new_pairs.difference(&stale_pairs) {
+                class_project_files_tree
+                    .update_and_fetch(
+                        qualified_class_name(package_name, class_name),
+                        |bytes: Option<&[u8]>| merge_string_into_list(path, bytes),
+                    )
+                    .map_err(|e| anyhow!(e.to_string()))?;
+            }
+            // Types the file still declares are affected too when it moves between modules.
+            affected_pairs.extend(stale_pairs);
+            affected_pairs.extend(new_pairs);
+
+            match change {
+                Some(file) => project_files_tree.insert(path, serde_json::to_vec(file)?)?,
+                None => project_files_tree.remove(path)?,
+            };
+        }
+        class_project_files_tree.flush()?;
+        project_files_tree.flush()?;
+
+        let mut declaring_files: BTreeMap<(String, String), Vec<String>> = BTreeMap::new();
+        for (class_name, package_name) in affected_pairs {
+            let paths = self.declaring_files(&class_name, &package_name)?;
+            declaring_files.insert((class_name, package_name), paths);
+        }
+        let removed: Vec<(String, String)> = declaring_files
+            .iter()
+            .filter(|(_, paths)| paths.is_empty())
+            .map(|(pair, _)| pair.clone())
+            .collect();
+        self.remove_class_pairs(&removed)?;
+        let added: Vec<(String, String, String)> = declaring_files
+            .iter()
+            .filter(|(_, paths)| !paths.is_empty())
+            .map(|((class_name, package_name), _)| {
+                (class_name.clone(), package_name.clone(), String::new())
+            })
+            .collect();
+        self.index_class_tuples(&added)?;
+        self.update_class_build_modules(&declaring_files)?;
+        self.update_class_scopes(&declaring_files)?;
+        Ok(())
+    }
+
+    /// Records the source location of each fully qualified class name. Tuples are
+    /// `(fqn, archive_path, entry_name)`. A later location for the same name replaces the earlier
+    /// one.
+    pub fn index_source_tuples(&self, tuples: &[(String, String, String)]) -> Result<()> {
+        let class_sources_tree = self.open_class_sources_tree();
+
+        for (fqn, archive, entry) in tuples {
+            let loc = SourceLocation {
+                archive: archive.clone(),
+                entry: entry.clone(),
+            };
+            class_sources_tree.insert(fqn, serde_json::to_vec(&loc)?)?;
+        }
+
+        class_sources_tree.flush()?;
+        Ok(())
+    }
+
+    /// Records which artifacts provide each fully qualified class name. Tuples are
+    /// `(fqn, groupId:artifactId:version)`.
+    pub fn index_coordinate_tuples(&self, tuples: &[(String, String)]) -> Result<()> {
+        let class_coordinates_tree = self.open_class_coordinates_tree();
+
+        for (fqn, coordinate) in tuples {
+            class_coordinates_tree
+                .update_and_fetch(fqn, |bytes: Option<&[u8]>| {
+                    merge_string_into_list(coordinate, bytes)
+                })
+                .map_err(|e| anyhow!(e.to_string()))?;
+        }
+
+        class_coordinates_tree.flush()?;
+        Ok(())
+    }
+
+    /// Records the scope, if any, that a dependency gives the fully qualified class names it
+    /// provides, replacing what it gave them when last indexed. A name several dependencies
+    /// provide keeps the scope of each. Names no dependency gave a scope are left unrecorded.
+    pub fn index_scope_tuples(&self, tuples: &[(String, String, Option<Scope>)]) -> Result<()> {
+        let class_scopes_tree = self.open_class_scopes_tree();
+
+        for (fqn, source, scope) in tuples {
+            class_scopes_tree
+                .update_and_fetch(fqn, |bytes: Option<&[u8]>| {
+                    if bytes.is_none() && scope.is_none() {
+                        return None;
+                    }
+                    let mut scopes = read_scopes(bytes);
+                    scopes.insert(source.clone(), *scope);
+                    write_scopes(&scopes)
+                })
+                .map_err(|e| anyhow!(e.to_string()))?;
+        }
+
+        class_scopes_tree.flush()?;
+        Ok(())
+    }
+
+    /// Records the module of each package. Tuples are `(package_name, module)`.
+    pub fn index_module_tuples(&self, tuples: &[(String, PackageModule)]) -> Result<()> {
+        let package_modules_tree = self.open_package_modules_tree();
+
+        for (package_name, module) in tuples {
+            package_modules_tree.insert(package_name, serde_json::to_vec(module)?)?;
+        }
+
+        package_modules_tree.flush()?;
+        Ok(())
+    }
+
     pub fn query_class_index(&self, class_name: &str) -> Result<HashMap<String, Vec<String>>> {
+        self.query_class_index_with(class_name, &QueryFilter::default())
+    }
+
+    pub fn query_class_index_with(
+        &self,
+        class_name: &str,
+        filter: &QueryFilter,
+    ) -> Result<HashMap<String, Vec<String>>> {
         let maybe_val = self.open_class_packages_tree().get(class_name)?;
         let mut results: HashMap<String, Vec<String>> = HashMap::new();
         match maybe_val {
@@ -103,12 +826,66 @@ impl<'a> Index<'a> {
                 results.insert(class_name.to_string(), Vec::new());
             }
             Some(val_bytes) => {
-                results.insert(class_name.to_string(), serde_json::from_slice(&val_bytes)?);
+                let mut packages: Vec<String> = serde_json::from_slice(&val_bytes)?;
+                if !filter.include_unexported {
+                    let modules = self.query_package_modules(&packages)?;
+                    packages.retain(|p| modules.get(p).map(|m| m.exported).unwrap_or(true));
+                }
+                if let Some(visible) = filter.visible_build_modules.as_ref() {
+                    let class_build_modules_tree = self.open_class_build_modules_tree();
+                    let mut visible_packages: Vec<String> = Vec::new();
+                    for package_name in packages {
+                        let declaring_modules: Option<Vec<String>> = class_build_modules_tree
+                            .get(qualified_class_name(&package_name, class_name))?
+                            .map(|bytes| serde_json::from_slice(&bytes))
+                            .transpose()?;
+                        let is_visible = declaring_modules
+                            .map(|modules| modules.iter().any(|m| visible.contains(m)))
+                            .unwrap_or(true);
+                        if is_visible {
+                            visible_packages.push(package_name);
+                        }
+                    }
+                    packages = visible_packages;
+                }
+                if let Some(query_scope) = filter.scope {
+                    let class_scopes_tree = self.open_class_scopes_tree();
+                    let mut visible_packages: Vec<String> = Vec::new();
+                    for package_name in packages {
+                        let scopes = read_scopes(
+                            class_scopes_tree
+                                .get(qualified_class_name(&package_name, class_name))?
+                                .as_deref(),
+                        );
+                        let is_visible = scopes_visible_from(&scopes, query_scope);
+                        if is_visible {
+                            visible_packages.push(package_name);
+                        }
+                    }
+                    packages = visible_packages;
+                }
+                results.insert(class_name.to_string(), packages);
             }
         };
         Ok(results)
     }
 
+    /// Looks up the modules of the given packages. Packages indexed without module information
+    /// are absent from the result.
+    pub fn query_package_modules(
+        &self,
+        packages: &[String],
+    ) -> Result<HashMap<String, PackageModule>> {
+        let package_modules_tree = self.open_package_modules_tree();
+        let mut results: HashMap<String, PackageModule> = HashMap::new();
+        for package_name in packages {
+            if let Some(val_bytes) = package_modules_tree.get(package_name)? {
+                results.insert(package_name.clone(), serde_json::from_slice(&val_bytes)?);
+            }
+        }
+        Ok(results)
+    }
+
     pub fn query_package_index(&self, package_name: &str) -> Result<HashMap<String, Vec<String>>> {
         let maybe_val = self.open_package_contents_tree().get(package_name)?;
         let mut results: HashMap<String, Vec<String>> = HashMap::new();
@@ -125,6 +902,48 @@ impl<'a> Index<'a> {
         };
         Ok(results)
     }
+
+    pub fn query_source_index(&self, fqn: &str) -> Result<HashMap<String, Vec<SourceLocation>>> {
+        let maybe_val = self.open_class_sources_tree().get(fqn)?;
+        let mut results: HashMap<String, Vec<SourceLocation>> = HashMap::new();
+        match maybe_val {
+            None => {
+                results.insert(fqn.to_string(), Vec::new());
+            }
+            Some(val_bytes) => {
+                results.insert(fqn.to_string(), vec![serde_json::from_slice(&val_bytes)?]);
+            }
+        };
+        Ok(results)
+    }
+
+    /// Answers which artifacts provide a fully qualified class name. Only the newest locally
+    /// available version of each `groupId:artifactId` is returned.
+    pub fn query_coordinate_index(&self, fqn: &str) -> Result<HashMap<String, Vec<String>>> {
+        let maybe_val = self.open_class_coordinates_tree().get(fqn)?;
+        let coordinates: Vec<String> = match maybe_val {
+            None => Vec::new(),
+            Some(val_bytes) => serde_json::from_slice(&val_bytes)?,
+        };
+
+        let mut newest: BTreeMap<String, Coordinate> = BTreeMap::new();
+        for coordinate in coordinates.iter().filter_map(|c| Coordinate::parse(c).ok()) {
+            let is_newer = newest
+                .get(&coordinate.key())
+                .map(|prev| maven::compare_versions(&coordinate.version, &prev.version).is_gt())
+                .unwrap_or(true);
+            if is_newer {
+                newest.insert(coordinate.key(), coordinate);
+            }
+        }
+
+        let mut results: HashMap<String, Vec<String>> = HashMap::new();
+        results.insert(
+            fqn.to_string(),
+            newest.values().map(|c| c.to_string()).collect(),
+        );
+        Ok(results)
+    }
 }
 
 // TODO: This should be split up. Half of this should be in `impl Index`, returning an iterator.
@@ -157,43 +976,390 @@ pub fn enumerate_indexes(index: &Index) -> Result<()> {
             }
         }
     }
-
-    Ok(())
+
+    Ok(())
+}
+
+/// Pairs each package found in an archive with the module the archive declares. Entries of
+/// nested archives are left out since the outer archive's descriptor does not cover them.
+fn module_tuples(
+    descriptor: &ModuleDescriptor,
+    tuples: &[(String, String, String)],
+) -> Vec<(String, PackageModule)> {
+    tuples
+        .iter()
+        .filter(|(_, _, entry)| !entry.contains("!/"))
+        .map(|(_, package_name, _)| package_name.clone())
+        .collect::<BTreeSet<String>>()
+        .into_iter()
+        .map(|package_name| {
+            let module = PackageModule {
+                module: descriptor.name.clone(),
+                exported: descriptor.exports_package(&package_name),
+            };
+            (package_name, module)
+        })
+        .collect()
+}
+
+/// Turns the path of a class file relative to its classpath root (`org/junit/Test.class`) into a
+/// `(class_name, package_name, entry_name)` tuple. Inner classes and module descriptors are left
+/// out.
+fn class_tuple_for_entry(filename: &str) -> Option<(String, String, String)> {
+    if filename.contains('$') || filename == MODULE_INFO_CLASS {
+        return None;
+    }
+    let stub = filename.strip_suffix(".class")?;
+    let mut parts = stub.split('/').collect::<Vec<&str>>();
+    match parts.len() {
+        0..=1 => {
+            eprintln!(
+                "Skipping because it lack enough path components: '{}'",
+                filename
+            );
+            None
+        }
+        _ => {
+            let class_name = parts.pop()?;
+            let package_name = parts.join(".");
+            Some((class_name.to_string(), package_name, filename.to_string()))
+        }
+    }
+}
+
+/// Names a class file from its contents when Kotlin or Scala compiled it, with `stub` being its
+/// path relative to the classpath root. Returns `None` when the usual naming applies.
+fn jvm_lang_tuples(
+    stub: &str,
+    bytes: &[u8],
+    entry_name: &str,
+) -> Option<Vec<(String, String, String)>> {
+    let (package_path, _) = stub.rsplit_once('/')?;
+    let class_file = ClassFile::parse(bytes).ok()?;
+    let names = jvmlang::source_names(&class_file)?;
+    let package_name = package_path.replace('/', ".");
+    Some(
+        names
+            .into_iter()
+            .map(|name| (name, package_name.clone(), entry_name.to_string()))
+            .collect(),
+    )
+}
+
+pub fn is_indexable_archive(path: &Path) -> bool {
+    path.to_str()
+        .map(|p| p.to_lowercase())
+        .map(|p| ARCHIVE_SUFFIXES.iter().any(|suffix| p.ends_with(suffix)))
+        .unwrap_or(false)
+}
+
+enum EntryRole<'a> {
+    Class(&'a str),
+    NestedArchive,
+}
+
+fn entry_role(filename: &str, is_aar: bool) -> EntryRole<'_> {
+    if is_aar
+        && (filename == AAR_CLASSES_ENTRY
+            || (filename.starts_with(AAR_LIBS_PREFIX) && is_jar_name(Path::new(filename))))
+    {
+        return EntryRole::NestedArchive;
+    }
+    for (classes_prefix, lib_prefix) in NESTED_LAYOUTS {
+        if let Some(stub) = filename.strip_prefix(classes_prefix) {
+            return EntryRole::Class(stub);
+        } else if filename.starts_with(lib_prefix) && is_jar_name(Path::new(filename)) {
+            return EntryRole::NestedArchive;
+        }
+    }
+    EntryRole::Class(filename)
+}
+
+/// Splits `META-INF/versions/<N>/<entry>` into the release and the entry it overlays.
+fn versioned_entry(filename: &str) -> Option<(u32, &str)> {
+    let (release, entry) = filename
+        .strip_prefix(VERSIONED_ENTRY_PREFIX)?
+        .split_once('/')?;
+    Some((release.parse().ok()?, entry))
+}
+
+/// Picks, for each entry of a multi-release jar that has versioned overlays, the archive entry
+/// that applies: the one for the highest release up to `target_release`, or for the highest of
+/// all without a target. The base entry counts as release 0.
+fn release_entries(filenames: &[String], target_release: Option<u32>) -> HashMap<&str, &String> {
+    let mut chosen: HashMap<&str, (u32, &String)> = HashMap::new();
+    for filename in filenames {
+        let (release, entry) = match versioned_entry(filename) {
+            Some((release, entry)) => (release, entry),
+            None => continue,
+        };
+        if target_release.is_some_and(|target| release > target) {
+            continue;
+        }
+        let is_newer = chosen
+            .get(entry)
+            .map(|(chosen_release, _)| release > *chosen_release)
+            .unwrap_or(true);
+        if is_newer {
+            chosen.insert(entry, (release, filename));
+        }
+    }
+    chosen
+        .into_iter()
+        .map(|(entry, (_, filename))| (entry, filename))
+        .collect()
+}
+
+/// Indexes an open archive. Nested library jars, and the jars inside an Android AAR, are read
+/// into memory and indexed in turn, their entries named `<nested jar>!/<entry>` like the URLs
+/// Spring Boot's loader uses. In a multi-release jar the versioned entries fold into their real
+/// packages, each entry indexed as of the highest release up to the index's target release.
+pub fn index_zip_reader<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    settings: &IndexSettings,
+) -> Result<Vec<(String, String, String)>> {
+    let multi_release = Manifest::from_zip(archive)
+        .and_then(|m| {
+            m.get("Multi-Release")
+                .map(|v| v.eq_ignore_ascii_case("true"))
+        })
+        .unwrap_or(false);
+    let is_aar = archive.file_names().any(|n| n == AAR_MARKER_ENTRY);
+
+    let filenames: Vec<String> = archive.file_names().map(String::from).collect();
+    let release_entries = if multi_release {
+        release_entries(&filenames, settings.target_release)
+    } else {
+        HashMap::new()
+    };
+    let mut accum: Vec<(String, String, String)> = Vec::new();
+    let mut nested_names: Vec<String> = Vec::new();
+    for filename in filenames.iter() {
+        let stub = if filename.starts_with(VERSIONED_ENTRY_PREFIX) {
+            // Outside of a multi-release jar these entries are plain resources.
+            match versioned_entry(filename) {
+                Some((_, entry)) if release_entries.get(entry) == Some(&filename) => entry,
+                _ => continue,
+            }
+        } else if release_entries
+            .get(filename.as_str())
+            .is_some_and(|chosen| *chosen != filename)
+        {
+            // A versioned entry overlays this one.
+            continue;
+        } else {
+            match entry_role(filename, is_aar) {
+                EntryRole::Class(stub) => stub,
+                EntryRole::NestedArchive => {
+                    nested_names.push(filename.to_string());
+                    continue;
+                }
+            }
+        };
+
+        if settings.jvm_lang_names && stub.ends_with(".class") {
+            let mut bytes: Vec<u8> = Vec::new();
+            archive.by_name(filename)?.read_to_end(&mut bytes)?;
+            if let Some(tuples) = jvm_lang_tuples(stub, &bytes, filename) {
+                accum.extend(tuples);
+                continue;
+            }
+        }
+
+        if let Some((class_name, package_name, _)) = class_tuple_for_entry(stub) {
+            accum.push((class_name, package_name, filename.to_string()));
+        }
+    }
+
+    for nested_name in nested_names {
+        let mut buf: Vec<u8> = Vec::new();
+        archive.by_name(&nested_name)?.read_to_end(&mut buf)?;
+        match ZipArchive::new(Cursor::new(buf)) {
+            Ok(mut nested) => {
+                for (class_name, package_name, entry) in index_zip_reader(&mut nested, settings)? {
+                    accum.push((
+                        class_name,
+                        package_name,
+                        format!("{}!/{}", nested_name, entry),
+                    ));
+                }
+            }
+            Err(e) => eprintln!(
+                "Skipping unreadable nested archive '{}': {}",
+                nested_name, e
+            ),
+        }
+    }
+    Ok(accum)
+}
+
+pub fn index_zip_archive(
+    path: &Path,
+    settings: &IndexSettings,
+) -> Result<Vec<(String, String, String)>> {
+    let inf = fs::File::open(path)?;
+    let mut archive = ZipArchive::new(inf)?;
+    index_zip_reader(&mut archive, settings)
+}
+
+/// Indexes an exploded classpath directory such as `target/classes`, where packages are
+/// subdirectories of the root.
+pub fn index_class_dir(
+    path: &Path,
+    settings: &IndexSettings,
+) -> Result<Vec<(String, String, String)>> {
+    let mut accum: Vec<(String, String, String)> = Vec::new();
+    for entry in WalkDir::new(path) {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let rel_path = entry.path().strip_prefix(path)?;
+        let rel_parts = rel_path
+            .iter()
+            .map(|p| p.to_str())
+            .collect::<Option<Vec<&str>>>();
+        let stub = match rel_parts {
+            Some(parts) => parts.join("/"),
+            None => continue,
+        };
+        if settings.jvm_lang_names && stub.ends_with(".class") {
+            let bytes = fs::read(entry.path())?;
+            if let Some(tuples) = jvm_lang_tuples(&stub, &bytes, &stub) {
+                accum.extend(tuples);
+                continue;
+            }
+        }
+        if let Some(tuple) = class_tuple_for_entry(&stub) {
+            accum.push(tuple);
+        }
+    }
+    Ok(accum)
+}
+
+/// Lists the Java sources in a `src.zip` as `(fqn, archive_path, entry_name)` tuples. JDK 9+
+/// images prefix each entry with its module (`java.base/java/util/HashMap.java`) while JDK 8 does
+/// not. Package directories never contain a '.', so a dotted first component is a module name.
+pub fn index_src_zip(path: &Path) -> Result<Vec<(String, String, String)>> {
+    let inf = fs::File::open(path)?;
+    let archive = ZipArchive::new(inf)?;
+    let archive_path = path
+        .to_str()
+        .ok_or_else(|| anyhow!("Source archive path is not UNICODE-compatible."))?;
+
+    let mut accum: Vec<(String, String, String)> = Vec::new();
+    for filename in archive.file_names() {
+        if let Some(stub) = filename.strip_suffix(".java") {
+            let mut parts = stub.split('/').collect::<Vec<&str>>();
+            if parts.len() > 1 && parts[0].contains('.') {
+                parts.remove(0);
+            }
+            match parts.last() {
+                None | Some(&"module-info") | Some(&"package-info") => {}
+                Some(_) => accum.push((
+                    parts.join("."),
+                    archive_path.to_string(),
+                    filename.to_string(),
+                )),
+            }
+        }
+    }
+    Ok(accum)
+}
+
+/// Lists the classes of a .jmod file, along with the module it describes. The zip following the
+/// JMOD header keeps class files under `classes/`; its other sections hold native libraries,
+/// commands and configuration.
+#[allow(clippy::type_complexity)]
+pub fn index_jmod(
+    path: &Path,
+) -> Result<(Vec<(String, String, String)>, Option<ModuleDescriptor>)> {
+    let contents = fs::read(path)?;
+    if !contents.starts_with(&JMOD_HEADER) {
+        return Err(anyhow!("Missing JMOD header."));
+    }
+    let mut archive = ZipArchive::new(Cursor::new(&contents[JMOD_HEADER.len()..]))?;
+
+    let mut module_info_bytes: Vec<u8> = Vec::new();
+    let descriptor = archive
+        .by_name("classes/module-info.class")
+        .ok()
+        .and_then(|mut entry| entry.read_to_end(&mut module_info_bytes).ok())
+        .and_then(|_| ModuleDescriptor::from_class_bytes(&module_info_bytes).ok());
+
+    let mut accum: Vec<(String, String, String)> = Vec::new();
+    for filename in archive.file_names() {
+        if let Some(stub) = filename.strip_prefix("classes/") {
+            if let Some((class_name, package_name, _)) = class_tuple_for_entry(stub) {
+                accum.push((class_name, package_name, filename.to_string()));
+            }
+        }
+    }
+    Ok((accum, descriptor))
 }
 
-pub fn index_zip_archive(path: &Path) -> Result<Vec<(String, String, String)>> {
+/// Lists the API of one Java release as recorded in a JDK's `lib/ct.sym`, which is what javac
+/// compiles against for `--release N`. Entries look like `9ABC/java.base/java/lang/Object.sig`
+/// from release 9 on, and `78/java/lang/Object.sig` before modules existed.
+pub fn index_ct_sym(path: &Path, release: u32) -> Result<Vec<(String, String, String)>> {
+    let release_char = ct_sym_release_char(release)
+        .ok_or_else(|| anyhow!("Release {} cannot be represented in ct.sym.", release))?;
     let inf = fs::File::open(path)?;
     let archive = ZipArchive::new(inf)?;
 
     let mut accum: Vec<(String, String, String)> = Vec::new();
     for filename in archive.file_names() {
-        if filename.ends_with(".class") && !filename.contains('$') {
-            if let Some(stub) = filename.strip_suffix(".class") {
-                let mut parts = stub.split('/').collect::<Vec<&str>>();
-                match parts.len() {
-                    0..=1 => eprintln!(
-                        "Skipping because it lack enough path components: '{}'",
-                        filename
-                    ),
-                    _ => {
-                        if let Some(class_name) = parts.pop() {
-                            let package_name = parts.join(".");
-                            accum.push((
-                                class_name.to_string(),
-                                package_name.to_string(),
-                                filename.to_string(),
-                            ));
-                        }
-                    }
-                }
-            }
+        let (releases, rest) = match filename.split_once('/') {
+            Some(split) => split,
+            None => continue,
+        };
+        if !releases.contains(release_char) || filename.contains('$') {
+            continue;
+        }
+        let mut parts = match rest.strip_suffix(".sig") {
+            Some(stub) => stub.split('/').collect::<Vec<&str>>(),
+            None => continue,
+        };
+        // As in src.zip, a dotted component can only be a module name.
+        if parts.len() > 1 && parts[0].contains('.') {
+            parts.remove(0);
+        }
+        if parts.len() < 2 {
+            continue;
         }
+        if let Some(class_name) = parts.pop() {
+            accum.push((
+                class_name.to_string(),
+                parts.join("."),
+                filename.to_string(),
+            ));
+        }
+    }
+    if accum.is_empty() {
+        // Indexing nothing would leave the index looking as if the release had no API at all.
+        let mut releases: Vec<u32> = archive
+            .file_names()
+            .filter_map(|filename| filename.split_once('/'))
+            .flat_map(|(releases, _)| releases.chars())
+            .filter_map(|c| c.to_digit(36))
+            .collect();
+        releases.sort();
+        releases.dedup();
+        let releases: Vec<String> = releases.iter().map(u32::to_string).collect();
+        bail!(
+            "{} has no entries for release {}. It holds releases {}.",
+            path.display(),
+            release,
+            releases.join(", ")
+        );
     }
     Ok(accum)
 }
 
+/// Lists the classes of a JDK modules image. Entry names are prefixed with their module, as in
+/// `java.base/java/util/HashMap.class`, which is also where `jimage extract` puts them.
 pub fn index_jimage(path: &Path) -> Result<Vec<(String, String, String)>> {
-    let jimage_child = Command::new("jimage")
+    let jimage_child = Command::new(jimage_command_for(path))
         .arg("list")
         .arg(path)
         .stdout(Stdio::piped())
@@ -209,11 +1375,11 @@ pub fn index_jimage(path: &Path) -> Result<Vec<(String, String, String)>> {
     // This intentionally omits the '$' character used to indicate inner classes.
     let class_entry_pat = Regex::new(r"^\s+([a-z0-9]+[/])+([A-Za-z0-9_]+).class").unwrap();
 
+    let mut curr_module = String::new();
     for ln_res in instream.lines() {
         if let Ok(ln) = ln_res {
             if let Some(caps) = module_header_pat.captures(&ln) {
-                // Not sure what to do with this, if anything.
-                let _curr_module = String::from(caps.get(1).unwrap().as_str());
+                curr_module = String::from(caps.get(1).unwrap().as_str());
             } else if let Some(caps) = class_entry_pat.captures(&ln) {
                 let captured_fname = caps.get(0).unwrap().as_str();
                 let filename = String::from(captured_fname.trim());
@@ -230,7 +1396,7 @@ pub fn index_jimage(path: &Path) -> Result<Vec<(String, String, String)>> {
                                 accum.push((
                                     class_name.to_string(),
                                     package_name.to_string(),
-                                    filename.to_string(),
+                                    format!("{}/{}", curr_module, filename),
                                 ));
                             }
                         }
@@ -243,68 +1409,645 @@ pub fn index_jimage(path: &Path) -> Result<Vec<(String, String, String)>> {
     Ok(accum)
 }
 
+/// Reads the descriptor of every module in a JDK modules image. `jimage list` only names the
+/// modules, so their module-info.class files are extracted to a scratch directory and parsed.
+pub fn jimage_module_descriptors(path: &Path) -> Result<HashMap<String, ModuleDescriptor>> {
+    // Removed on drop, whichever way this returns. Concurrent jobs each get their own.
+    let scratch_dir = tempfile::Builder::new().prefix("cpid-jimage-").tempdir()?;
+    let status = Command::new(jimage_command_for(path))
+        .arg("extract")
+        .arg("--include")
+        .arg("regex:.*/module-info\\.class")
+        .arg("--dir")
+        .arg(scratch_dir.path())
+        .arg(path)
+        .stdout(Stdio::null())
+        .status()?;
+
+    if !status.success() {
+        return Err(anyhow!("jimage extract failed: {}", status));
+    }
+
+    let mut descriptors: HashMap<String, ModuleDescriptor> = HashMap::new();
+    for entry in fs::read_dir(scratch_dir.path())? {
+        let module_dir = entry?.path();
+        if let Some(descriptor) = ModuleDescriptor::from_dir(&module_dir) {
+            descriptors.insert(descriptor.name.clone(), descriptor);
+        }
+    }
+    Ok(descriptors)
+}
+
+/// Pairs packages with the module named by the first component of their entries, for sources
+/// that keep every module in one file. Modules without a descriptor are assumed to export
+/// everything.
+fn module_tuples_by_entry_prefix(
+    tuples: &[(String, String, String)],
+    descriptors: &HashMap<String, ModuleDescriptor>,
+) -> Vec<(String, PackageModule)> {
+    tuples
+        .iter()
+        .filter_map(|(_, package_name, entry)| {
+            let module_name = entry.split('/').next().filter(|m| m.contains('.'))?;
+            Some((package_name.clone(), module_name.to_string()))
+        })
+        .collect::<BTreeSet<(String, String)>>()
+        .into_iter()
+        .map(|(package_name, module_name)| {
+            let exported = descriptors
+                .get(&module_name)
+                .map(|d| d.exports_package(&package_name))
+                .unwrap_or(true);
+            let module = PackageModule {
+                module: module_name,
+                exported,
+            };
+            (package_name, module)
+        })
+        .collect()
+}
+
+fn file_mtime(path: &Path) -> Result<u64> {
+    let modified = fs::metadata(path)?.modified()?;
+    Ok(modified.duration_since(UNIX_EPOCH)?.as_nanos() as u64)
+}
+
+fn content_hash(code: &str) -> String {
+    format!("{:x}", Sha256::digest(code.as_bytes()))
+}
+
+/// Crawls one project file, given its previous record if it has one. Returns `None` when the
+/// record still holds.
+fn crawl_project_file(
+    parser: &mut SourceParser,
+    path: &Path,
+    code: &String,
+    mtime: u64,
+    build_module: Option<String>,
+    previous: Option<&ProjectFile>,
+) -> Result<Option<ProjectFile>> {
+    let hash = content_hash(code);
+    if let Some(previous) = previous {
+        if previous.hash == hash {
+            // Touched but not edited, or moved between build modules, so the parse still holds.
+            let updated = ProjectFile {
+                mtime,
+                build_module,
+                ..previous.clone()
+            };
+            return Ok((&updated != previous).then_some(updated));
+        }
+    }
+    let pkg = parser.collect(path, code)?;
+    Ok(Some(ProjectFile {
+        mtime,
+        hash,
+        package_name: pkg.name.unwrap_or_default(),
+        types: pkg.contained_identifiers,
+        build_module,
+    }))
+}
+
+/// Names the build module holding a project file.
+fn build_module_key(modules: &[BuildModule], path: &Path) -> Option<String> {
+    multimodule::module_for_path(modules, path).map(|m| m.dir.to_string_lossy().into_owned())
+}
+
+/// Brings the index up to date with the sources under a project directory. Files whose mtime
+/// matches the last crawl are not read, files whose contents hash the same are not reparsed, and
+/// the types of deleted files are removed.
 pub fn reindex_project_path(index: &Index, indexed_project_path: &Path) -> Result<()> {
-    let packages = crate::project::crawl_project(indexed_project_path).or(Err(anyhow!(
-        "Failed to crawl contents of project directory."
-    )))?;
-    let mut tuples: Vec<(String, String, String)> = Vec::new();
-    for pkg in packages {
-        if let Some(pkg_name) = pkg.name {
-            for class_name in pkg.contained_identifiers {
-                tuples.push((class_name.clone(), pkg_name.clone(), String::new()));
+    let project_path = fs::canonicalize(indexed_project_path)?;
+    let config = ProjectConfig::load(&project_path)?;
+    let previous = index.project_files()?;
+    if previous.is_empty() && !index.open_class_packages_tree().is_empty() {
+        eprintln!(
+            "Index {} holds types but no crawled project files. Types of a project crawled by an \
+             older version are not removed with their files; drop the index and crawl again to \
+             clear them.",
+            index.name()
+        );
+    }
+    let modules = multimodule::detect_modules(&project_path).unwrap_or_else(|e| {
+        eprintln!(
+            "Crawling as a single module, the build failed to read: {}",
+            e
+        );
+        Vec::new()
+    });
+    index.store_build_modules(&project_path, &modules)?;
+    index
+        .open_project_roots_tree()
+        .insert(project_path.to_string_lossy().as_bytes(), &[])?;
+
+    let mut seen: BTreeSet<String> = BTreeSet::new();
+    let mut stale_files: Vec<(String, PathBuf, u64, Option<String>)> = Vec::new();
+    for path in project::source_files(&project_path, &config.crawl)? {
+        let key = path.to_string_lossy().into_owned();
+        seen.insert(key.clone());
+        let mtime = match file_mtime(&path) {
+            Ok(mtime) => mtime,
+            Err(_) => continue,
+        };
+        let build_module = build_module_key(&modules, &path);
+        let is_current = previous
+            .get(&key)
+            .map(|f| f.mtime == mtime && f.build_module == build_module)
+            .unwrap_or(false);
+        if !is_current {
+            stale_files.push((key, path, mtime, build_module));
+        }
+    }
+
+    // Each worker parses with its own parsers, the tree-sitter ones not being shareable. One made
+    // here fails the crawl up front if they can't be set up.
+    SourceParser::new()?;
+    let mut changes: BTreeMap<String, Option<ProjectFile>> = BTreeMap::new();
+    let files_total = stale_files.len();
+    let mut files_done = 0;
+    pool::for_each_parallel(
+        stale_files,
+        SourceParser::new,
+        |parser, (key, path, mtime, build_module)| {
+            let crawled = parser
+                .as_mut()
+                .map_err(|e| anyhow!("{}", e))
+                .and_then(|parser| {
+                    let code = fs::read_to_string(&path)?;
+                    crawl_project_file(
+                        parser,
+                        &path,
+                        &code,
+                        mtime,
+                        build_module,
+                        previous.get(&key),
+                    )
+                });
+            (key, crawled)
+        },
+        |(key, crawled)| {
+            files_done += 1;
+            index.report_progress(files_done, files_total, Path::new(&key));
+            match crawled {
+                Ok(Some(file)) => {
+                    changes.insert(key, Some(file));
+                }
+                Ok(None) => {}
+                Err(e) => eprintln!("Skipping {}: {}", key, e),
             }
+        },
+    );
+    for key in previous.keys() {
+        if Path::new(key).starts_with(&project_path) && !seen.contains(key) {
+            changes.insert(key.clone(), None);
         }
     }
-    index.index_class_tuples(&tuples).or(Err(anyhow!(
+
+    index.update_project_files(&changes).or(Err(anyhow!(
         "Failed to index contents of project directory."
-    )))?;
+    )))
+}
 
-    Ok(())
+/// Replaces what a single project file contributes to the index, as when an editor saves it.
+/// Given `contents`, those of an unsaved buffer, they are indexed instead of the file's. Their
+/// record carries no mtime, so the next crawl compares the file on disk against them. A file
+/// that no longer exists, or that a crawl of its project would leave out, has its types removed.
+/// The project is the innermost one crawled into the index, or else the one whose `.cpid.toml`
+/// is nearest above the file.
+pub fn reindex_project_file(index: &Index, path: &Path, contents: Option<&str>) -> Result<()> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| anyhow!("Not a file: {}", path.display()))?;
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let file_path = fs::canonicalize(parent)?.join(file_name);
+    if !project::is_source_file(&file_path) {
+        bail!("Not a source file: {}", file_path.display());
+    }
+    let key = file_path.to_string_lossy().into_owned();
+    let previous = index.project_file(&key)?;
+
+    let project_root = match index.project_root_for(&file_path)? {
+        Some(root) => Some(root),
+        None => ProjectConfig::discover(&file_path)?.map(|project| project.root),
+    };
+    let is_crawled = match project_root {
+        Some(root) => {
+            let config = ProjectConfig::load(&root)?;
+            project::is_crawled_file(&root, &config.crawl, &file_path)?
+        }
+        None => true,
+    };
+
+    let (code, mtime) = match contents {
+        Some(code) if is_crawled => (code.to_string(), 0),
+        None if is_crawled && file_path.exists() => {
+            (fs::read_to_string(&file_path)?, file_mtime(&file_path)?)
+        }
+        _ => {
+            let mut changes: BTreeMap<String, Option<ProjectFile>> = BTreeMap::new();
+            if previous.is_some() {
+                changes.insert(key, None);
+            }
+            return index.update_project_files(&changes);
+        }
+    };
+
+    let build_module = build_module_key(&index.build_modules()?, &file_path);
+    let mut parser = SourceParser::new()?;
+    let mut changes: BTreeMap<String, Option<ProjectFile>> = BTreeMap::new();
+    if let Some(file) = crawl_project_file(
+        &mut parser,
+        &file_path,
+        &code,
+        mtime,
+        build_module,
+        previous.as_ref(),
+    )? {
+        changes.insert(key, Some(file));
+    }
+    index.update_project_files(&changes)
 }
 
-pub fn reindex_jar_dir(index: &Index, indexed_dir_path: &Path) -> Result<()> {
-    walk_file_tree(indexed_dir_path, &|entry: &fs::DirEntry| {
-        if let Some(entry_path) = entry.path().as_path().to_str() {
-            if entry_path.ends_with(".jar") {
-                index_zip_archive(entry.path().as_path())
-                    .and_then(|tuples| index.index_class_tuples(&tuples))
-                    .map_err(|e| {
-                        anyhow!(
-                            "Error: Could not store index entries for archive: {}: {}",
-                            entry_path,
-                            e
-                        )
-                    })?;
+/// Results that reach the writer are held until this many tuples have gathered, and then stored
+/// together.
+const BATCH_TUPLES: usize = 100_000;
+
+/// Tuples read from archives and class directories, waiting to be stored in an index.
+#[derive(Debug, Default)]
+struct IndexBatch {
+    classes: Vec<(String, String, String)>,
+    modules: Vec<(String, PackageModule)>,
+    coordinates: Vec<(String, String)>,
+    sources: Vec<(String, String, String)>,
+    scopes: Vec<(String, String, Option<Scope>)>,
+}
+
+impl IndexBatch {
+    fn len(&self) -> usize {
+        self.classes.len()
+            + self.modules.len()
+            + self.coordinates.len()
+            + self.sources.len()
+            + self.scopes.len()
+    }
+
+    fn extend(&mut self, other: IndexBatch) {
+        self.classes.extend(other.classes);
+        self.modules.extend(other.modules);
+        self.coordinates.extend(other.coordinates);
+        self.sources.extend(other.sources);
+        self.scopes.extend(other.scopes);
+    }
+
+    /// Attaches the coordinate of the artifact they came from to each of the batch's classes.
+    fn with_coordinate(mut self, coordinate: &Coordinate) -> Self {
+        let coordinate_str = coordinate.to_string();
+        self.coordinates = self
+            .classes
+            .iter()
+            .map(|(class_name, package_name, _)| {
+                (
+                    qualified_class_name(package_name, class_name),
+                    coordinate_str.clone(),
+                )
+            })
+            .collect();
+        self
+    }
+
+    /// Tags each of the batch's classes with the scope of the dependency at `source` they came
+    /// from. Without a scope, the tag the dependency gave them before is cleared.
+    fn with_scope(mut self, source: &Path, scope: Option<Scope>) -> Self {
+        let source = source.to_string_lossy().into_owned();
+        self.scopes = self
+            .classes
+            .iter()
+            .map(|(class_name, package_name, _)| {
+                (
+                    qualified_class_name(package_name, class_name),
+                    source.clone(),
+                    scope,
+                )
+            })
+            .collect();
+        self
+    }
+
+    /// Writes out the batch, leaving it empty.
+    fn store(&mut self, index: &Index) -> Result<()> {
+        let batch = std::mem::take(self);
+        index.index_class_tuples(&batch.classes)?;
+        index.index_module_tuples(&batch.modules)?;
+        index.index_coordinate_tuples(&batch.coordinates)?;
+        index.index_source_tuples(&batch.sources)?;
+        index.index_scope_tuples(&batch.scopes)
+    }
+}
+
+/// Reads the classes of an archive, and the module of their packages when the archive declares
+/// one.
+fn read_archive(path: &Path, settings: &IndexSettings) -> Result<IndexBatch> {
+    let inf = fs::File::open(path)?;
+    let mut archive = ZipArchive::new(inf)?;
+    let classes = index_zip_reader(&mut archive, settings)?;
+    let modules = ModuleDescriptor::from_zip(&mut archive)
+        .map(|descriptor| module_tuples(&descriptor, &classes))
+        .unwrap_or_default();
+    Ok(IndexBatch {
+        classes,
+        modules,
+        ..IndexBatch::default()
+    })
+}
+
+fn read_class_dir(path: &Path, settings: &IndexSettings) -> Result<IndexBatch> {
+    let classes = index_class_dir(path, settings)?;
+    let modules = ModuleDescriptor::from_dir(path)
+        .map(|descriptor| module_tuples(&descriptor, &classes))
+        .unwrap_or_default();
+    Ok(IndexBatch {
+        classes,
+        modules,
+        ..IndexBatch::default()
+    })
+}
+
+/// Reads a single classpath element, either an archive or an exploded class directory.
+fn read_classpath_element(element_path: &Path, settings: &IndexSettings) -> Result<IndexBatch> {
+    let result = if element_path.is_dir() {
+        read_class_dir(element_path, settings)
+    } else {
+        read_archive(element_path, settings)
+    };
+    result.map_err(|e| {
+        anyhow!(
+            "Error: Could not store index entries for archive: {}: {}",
+            element_path.display(),
+            e
+        )
+    })
+}
+
+/// Reads an archive and attaches its coordinate to each of its classes.
+fn read_archive_with_coordinate(
+    path: &Path,
+    settings: &IndexSettings,
+    coordinate: &Coordinate,
+) -> Result<IndexBatch> {
+    Ok(read_archive(path, settings)?.with_coordinate(coordinate))
+}
+
+/// Reads items on the worker pool while this thread writes what they hold into the index in
+/// batches, reporting progress as each is read. Items that fail to read are returned rather than
+/// stopping the others.
+fn reindex_in_parallel<I: Send>(
+    index: &Index,
+    items: Vec<I>,
+    path_of: impl Fn(&I) -> PathBuf + Sync,
+    read: impl Fn(I) -> Result<IndexBatch> + Sync,
+) -> Result<Vec<anyhow::Error>> {
+    let mut batch = IndexBatch::default();
+    let mut read_errors: Vec<anyhow::Error> = Vec::new();
+    let mut write_result: Result<()> = Ok(());
+    let archives_total = items.len();
+    let mut archives_done = 0;
+    pool::for_each_parallel(
+        items,
+        || (),
+        |_, item| (path_of(&item), read(item)),
+        |(path, result)| {
+            match result {
+                Ok(read_batch) => {
+                    batch.extend(read_batch);
+                    if batch.len() >= BATCH_TUPLES && write_result.is_ok() {
+                        write_result = batch.store(index);
+                    }
+                }
+                Err(e) => read_errors.push(e),
             }
+            archives_done += 1;
+            index.report_progress(archives_done, archives_total, &path);
+        },
+    );
+    write_result?;
+    batch.store(index)?;
+    Ok(read_errors)
+}
+
+/// Indexes classpath elements in parallel, tagging each element's classes with its scope, if it
+/// has one. An unreadable element no longer stops the others: every readable one is stored, and
+/// then the indexing fails with an error naming each element that could not be read.
+fn reindex_classpath_elements(
+    index: &Index,
+    elements: Vec<(PathBuf, Option<Scope>)>,
+) -> Result<()> {
+    let settings = index.settings()?;
+    let elements_total = elements.len();
+    let read_errors = reindex_in_parallel(
+        index,
+        elements,
+        |(element_path, _)| element_path.clone(),
+        |(element_path, scope)| {
+            read_classpath_element(&element_path, &settings)
+                .map(|batch| batch.with_scope(&element_path, scope))
+        },
+    )?;
+    if read_errors.is_empty() {
+        return Ok(());
+    }
+    let messages: Vec<String> = read_errors.iter().map(|e| e.to_string()).collect();
+    bail!(
+        "{} of {} classpath elements could not be read, the rest were indexed:\n{}",
+        read_errors.len(),
+        elements_total,
+        messages.join("\n")
+    )
+}
+
+fn reindex_classpath_element(index: &Index, element_path: &Path) -> Result<()> {
+    reindex_classpath_elements(index, vec![(element_path.to_path_buf(), None)])
+}
+
+/// Lists the files under a directory tree that `accept` picks.
+fn list_files(dir: &Path, accept: impl Fn(&Path) -> bool) -> Result<Vec<PathBuf>> {
+    let found: RefCell<Vec<PathBuf>> = RefCell::new(Vec::new());
+    walk_file_tree(dir, &|entry: &fs::DirEntry| {
+        let entry_path = entry.path();
+        if accept(&entry_path) {
+            found.borrow_mut().push(entry_path);
         }
         Ok(())
-    })
-    .expect("Directory scanning.");
-    Ok(())
+    })?;
+    Ok(found.into_inner())
+}
+
+pub fn reindex_jar_dir(index: &Index, indexed_dir_path: &Path) -> Result<()> {
+    let jars = list_files(indexed_dir_path, is_indexable_archive)?
+        .into_iter()
+        .map(|jar_path| (jar_path, None))
+        .collect();
+    reindex_classpath_elements(index, jars)
 }
 
-pub fn reindex_classpath(index: &Index, class_path: &str) -> Result<()> {
-    for jar_path_name in class_path.split(':') {
-        if jar_path_name.ends_with(".jar") {
-            // TODO: This chain needs to be pulled out into a new function to consolidate with
-            // reindex_jar_dir
-            index_zip_archive(Path::new(jar_path_name))
-                .and_then(|tuples| index.index_class_tuples(&tuples))
+/// Indexes the archives and class directories of a classpath, tagging their classes with `scope`
+/// when the classpath is one of a project's dependency scopes.
+pub fn reindex_classpath(index: &Index, class_path: &str, scope: Option<Scope>) -> Result<()> {
+    let elements = crate::classpath::expand_classpath(class_path)
+        .into_iter()
+        .filter(|element_path| is_indexable_archive(element_path) || element_path.is_dir())
+        .map(|element_path| (element_path, scope))
+        .collect();
+    reindex_classpath_elements(index, elements)
+}
+
+/// Indexes every artifact jar in a local Maven repository, attaching its coordinate to each of
+/// its classes. Local repositories routinely hold truncated downloads, so an unreadable jar is
+/// reported and skipped rather than ending the walk.
+pub fn reindex_m2(index: &Index, repo_path: &Path) -> Result<()> {
+    let settings = index.settings()?;
+    let jars = list_files(repo_path, |_| true)?
+        .into_iter()
+        .filter_map(|path| Some((maven::coordinate_for_jar(repo_path, &path)?, path)))
+        .collect::<Vec<(Coordinate, PathBuf)>>();
+    let read_errors = reindex_in_parallel(
+        index,
+        jars,
+        |(_, path)| path.clone(),
+        |(coordinate, path)| {
+            read_archive_with_coordinate(&path, &settings, &coordinate)
+                .map(|batch| batch.with_scope(&path, None))
                 .map_err(|e| {
                     anyhow!(
                         "Error: Could not store index entries for archive: {}: {}",
-                        jar_path_name,
+                        path.display(),
                         e
                     )
-                })?;
-        }
+                })
+        },
+    )?;
+    for e in read_errors {
+        eprintln!("{}", e);
+    }
+    Ok(())
+}
+
+/// Indexes Gradle's module cache. Class jars are attributed to their coordinate, sources jars
+/// feed the source index and javadoc jars are ignored.
+pub fn reindex_gradle_cache(index: &Index, cache_path: &Path) -> Result<()> {
+    let settings = index.settings()?;
+    let jars = list_files(cache_path, |_| true)?
+        .into_iter()
+        .filter_map(|path| {
+            let (coordinate, kind) = gradle::classify_cache_jar(cache_path, &path)?;
+            Some((coordinate, kind, path))
+        })
+        .collect::<Vec<(Coordinate, CacheJarKind, PathBuf)>>();
+    let read_errors = reindex_in_parallel(
+        index,
+        jars,
+        |(_, _, path)| path.clone(),
+        |(coordinate, kind, path)| {
+            let result = match kind {
+                CacheJarKind::Classes => {
+                    read_archive_with_coordinate(&path, &settings, &coordinate)
+                        .map(|batch| batch.with_scope(&path, None))
+                }
+                CacheJarKind::Sources => index_src_zip(&path).map(|sources| IndexBatch {
+                    sources,
+                    ..IndexBatch::default()
+                }),
+                CacheJarKind::Javadoc => Ok(IndexBatch::default()),
+            };
+            result.map_err(|e| {
+                anyhow!(
+                    "Error: Could not store index entries for archive: {}: {}",
+                    path.display(),
+                    e
+                )
+            })
+        },
+    )?;
+    for e in read_errors {
+        eprintln!("{}", e);
     }
     Ok(())
 }
 
+/// Resolves the dependencies of a Maven project from the local repository alone and indexes the
+/// resulting jars, tagging their classes with the scope each was resolved in.
+pub fn reindex_pom(index: &Index, pom_path: &Path, repo_path: &Path) -> Result<()> {
+    let artifacts = crate::pom::Resolver::new(repo_path).resolve_project(pom_path)?;
+    let elements = artifacts
+        .into_iter()
+        .map(|artifact| {
+            let scope = Scope::from_maven(&artifact.scope);
+            (artifact.jar_path, Some(scope))
+        })
+        .collect();
+    reindex_classpath_elements(index, elements)
+}
+
+/// Reindexes what a project's `.cpid.toml` declares: its classpath into the dependencies index,
+/// then its sources into the sources index.
+pub fn reindex_configured_project(
+    db: &sled::Db,
+    project: &ConfiguredProject,
+    progress: Option<&ProgressFn>,
+) -> Result<()> {
+    let dependencies_name = project.dependencies_index();
+    let sources_name = project.sources_index();
+    if dependencies_name == sources_name {
+        bail!(
+            "The dependencies index of {} is its sources index, {}. A crawl removes the types of \
+             deleted source files from the sources index, taking any dependency's copy with them; \
+             name a separate dependencies index.",
+            project.root.display(),
+            sources_name
+        );
+    }
+    let mut dependencies = Index::new(db, &dependencies_name);
+    if let Some(progress) = progress {
+        dependencies = dependencies.with_progress(progress);
+    }
+    let classpath = &project.config.classpath;
+    if let Some(pom) = classpath.pom.as_ref() {
+        let repo_path = maven::default_local_repository()
+            .ok_or_else(|| anyhow!("Cannot locate the local Maven repository."))?;
+        reindex_pom(&dependencies, &project.root.join(pom), &repo_path)?;
+    }
+    for (scope, entries) in [
+        (Scope::Compile, &classpath.compile),
+        (Scope::Runtime, &classpath.runtime),
+        (Scope::Test, &classpath.test),
+    ] {
+        if !entries.is_empty() {
+            reindex_classpath(&dependencies, &project.classpath_expr(entries), Some(scope))?;
+        }
+    }
+
+    let mut sources = Index::new(db, &sources_name);
+    if let Some(progress) = progress {
+        sources = sources.with_progress(progress);
+    }
+    reindex_project_path(&sources, &project.root)
+}
+
 pub fn reindex_jimage(index: &Index, jimage_path: &Path) -> Result<()> {
     index_jimage(jimage_path)
-        .and_then(|tuples| index.index_class_tuples(&tuples))
+        .and_then(|tuples| {
+            index.index_class_tuples(&tuples)?;
+            let descriptors = jimage_module_descriptors(jimage_path).unwrap_or_else(|e| {
+                eprintln!(
+                    "Module exports unavailable, treating all as exported: {}",
+                    e
+                );
+                HashMap::new()
+            });
+            index.index_module_tuples(&module_tuples_by_entry_prefix(&tuples, &descriptors))
+        })
         .map_err(|e| {
             anyhow!(
                 "Error: Could not store index entries for image: {}: {}",
@@ -312,6 +2055,108 @@ pub fn reindex_jimage(index: &Index, jimage_path: &Path) -> Result<()> {
                 e
             )
         })?;
+
+    // JDK images keep the sources for the modules image next to it, as lib/src.zip.
+    let src_zip_path = jimage_path.with_file_name("src.zip");
+    if src_zip_path.is_file() {
+        reindex_jdk_src(index, &src_zip_path)?;
+    }
+    index.report_progress(1, 1, jimage_path);
+    Ok(())
+}
+
+/// Indexes a single .jmod file, or every .jmod file below a directory such as `$JAVA_HOME/jmods`.
+pub fn reindex_jmod(index: &Index, jmod_path: &Path) -> Result<()> {
+    let reindex_one = |path: &Path| {
+        index_jmod(path)
+            .and_then(|(tuples, descriptor)| {
+                index.index_class_tuples(&tuples)?;
+                match descriptor {
+                    Some(descriptor) => {
+                        index.index_module_tuples(&module_tuples(&descriptor, &tuples))
+                    }
+                    None => Ok(()),
+                }
+            })
+            .map_err(|e| {
+                anyhow!(
+                    "Error: Could not store index entries for jmod: {}: {}",
+                    path.display(),
+                    e
+                )
+            })
+    };
+
+    let jmod_paths = if jmod_path.is_dir() {
+        list_files(jmod_path, |path| {
+            path.extension().map(|ext| ext == "jmod").unwrap_or(false)
+        })?
+    } else {
+        vec![jmod_path.to_path_buf()]
+    };
+    for (done, path) in jmod_paths.iter().enumerate() {
+        reindex_one(path)?;
+        index.report_progress(done + 1, jmod_paths.len(), path);
+    }
+    Ok(())
+}
+
+pub fn reindex_ct_sym(index: &Index, ct_sym_path: &Path, release: u32) -> Result<()> {
+    index_ct_sym(ct_sym_path, release)
+        .and_then(|tuples| {
+            index.index_class_tuples(&tuples)?;
+            // ct.sym only holds exported API, so every package it names is exported. Its entries
+            // start with the releases, then the module.
+            let module_entries = tuples
+                .iter()
+                .map(|(c, p, entry)| {
+                    let module_entry = entry.split_once('/').map(|(_, e)| e).unwrap_or(entry);
+                    (c.clone(), p.clone(), module_entry.to_string())
+                })
+                .collect::<Vec<(String, String, String)>>();
+            index.index_module_tuples(&module_tuples_by_entry_prefix(
+                &module_entries,
+                &HashMap::new(),
+            ))
+        })
+        .map_err(|e| {
+            anyhow!(
+                "Error: Could not store index entries for ct.sym: {}: {}",
+                ct_sym_path.display(),
+                e
+            )
+        })
+}
+
+/// Indexes a discovered JDK's class library and sources.
+pub fn reindex_jdk_install(index: &Index, jdk: &JdkInstall) -> Result<()> {
+    if let Some(jimage_path) = jdk.jimage_path() {
+        // This picks up lib/src.zip by itself.
+        reindex_jimage(index, &jimage_path)
+    } else if let Some(rt_jar_path) = jdk.rt_jar_path() {
+        reindex_classpath_element(index, &rt_jar_path)?;
+        match jdk.src_zip_path() {
+            Some(src_zip_path) => reindex_jdk_src(index, &src_zip_path),
+            None => Ok(()),
+        }
+    } else {
+        Err(anyhow!(
+            "{} has neither lib/modules nor jre/lib/rt.jar.",
+            jdk.home.display()
+        ))
+    }
+}
+
+pub fn reindex_jdk_src(index: &Index, src_zip_path: &Path) -> Result<()> {
+    index_src_zip(src_zip_path)
+        .and_then(|tuples| index.index_source_tuples(&tuples))
+        .map_err(|e| {
+            anyhow!(
+                "Error: Could not store source entries for archive: {}: {}",
+                src_zip_path.display(),
+                e
+            )
+        })?;
     Ok(())
 }
 
@@ -327,3 +2172,680 @@ pub fn walk_file_tree(dir: &Path, cb: &dyn Fn(&fs::DirEntry) -> Result<()>) -> R
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("fixtures")
+            .join(name)
+    }
+
+    fn temporary_db() -> sled::Db {
+        sled::Config::new().temporary(true).open().unwrap()
+    }
+
+    #[test]
+    fn archive_suffixes_match_in_any_case() {
+        for name in ["a.jar", "b.JAR", "app.WAR", "lib.Aar", "/x/y.war"] {
+            assert!(is_indexable_archive(Path::new(name)), "{}", name);
+        }
+        for name in ["a.jarx", "notes.txt", "jar", "classes"] {
+            assert!(!is_indexable_archive(Path::new(name)), "{}", name);
+        }
+    }
+
+    #[test]
+    fn src_zip_entries_are_named_without_their_module() {
+        let src_zip = fixture("jdk-src.zip");
+        let mut tuples = index_src_zip(&src_zip).unwrap();
+        tuples.sort();
+        let names: Vec<(&str, &str)> = tuples
+            .iter()
+            .map(|(fqn, _, entry)| (fqn.as_str(), entry.as_str()))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("java.lang.String", "java.base/java/lang/String.java"),
+                ("java.sql.Connection", "java.sql/java/sql/Connection.java"),
+                ("java.util.Map", "java.base/java/util/Map.java"),
+            ]
+        );
+    }
+
+    #[test]
+    fn source_index_answers_with_archive_entries() {
+        let db = temporary_db();
+        let index = Index::new(&db, "src");
+        let src_zip = fixture("jdk-src.zip");
+        reindex_jdk_src(&index, &src_zip).unwrap();
+
+        let results = index.query_source_index("java.util.Map").unwrap();
+        assert_eq!(
+            results["java.util.Map"],
+            vec![SourceLocation {
+                archive: src_zip.to_str().unwrap().to_string(),
+                entry: "java.base/java/util/Map.java".to_string(),
+            }]
+        );
+        let results = index.query_source_index("java.util.Missing").unwrap();
+        assert!(results["java.util.Missing"].is_empty());
+    }
+
+    fn packages_of(index: &Index, class_name: &str) -> Vec<String> {
+        index.query_class_index(class_name).unwrap()[class_name].clone()
+    }
+
+    fn write_source(dir: &Path, name: &str, code: &str) -> PathBuf {
+        let path = dir.join(name);
+        fs::write(&path, code).unwrap();
+        fs::canonicalize(path).unwrap()
+    }
+
+    fn write_jar(path: &Path, entries: &[&str]) {
+        let mut jar = zip::write::ZipWriter::new(fs::File::create(path).unwrap());
+        for entry in entries {
+            jar.start_file(*entry, zip::write::FileOptions::default())
+                .unwrap();
+        }
+        jar.finish().unwrap();
+    }
+
+    fn packages_from(index: &Index, class_name: &str, scope: Scope) -> Vec<String> {
+        let filter = QueryFilter {
+            scope: Some(scope),
+            ..QueryFilter::default()
+        };
+        index.query_class_index_with(class_name, &filter).unwrap()[class_name].clone()
+    }
+
+    #[test]
+    fn edited_project_file_replaces_its_types() {
+        let db = temporary_db();
+        let index = Index::new(&db, "proj");
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_source(dir.path(), "Foo.java", "package a; class Foo {}");
+        reindex_project_file(&index, &path, None).unwrap();
+        assert_eq!(packages_of(&index, "Foo"), vec!["a"]);
+
+        write_source(
+            dir.path(),
+            "Foo.java",
+            "package b; class Foo {} class Helper {}",
+        );
+        reindex_project_file(&index, &path, None).unwrap();
+        assert_eq!(packages_of(&index, "Foo"), vec!["b"]);
+        assert_eq!(packages_of(&index, "Helper"), vec!["b"]);
+    }
+
+    #[test]
+    fn touched_project_file_keeps_its_types() {
+        let db = temporary_db();
+        let index = Index::new(&db, "proj");
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_source(dir.path(), "Foo.java", "package a; class Foo {}");
+        reindex_project_path(&index, dir.path()).unwrap();
+        let crawled = index.project_file(path.to_str().unwrap()).unwrap().unwrap();
+
+        let touched_at = UNIX_EPOCH + std::time::Duration::from_secs(1_000_000_000);
+        fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(touched_at)
+            .unwrap();
+        reindex_project_path(&index, dir.path()).unwrap();
+        let touched = index.project_file(path.to_str().unwrap()).unwrap().unwrap();
+        assert_ne!(touched.mtime, crawled.mtime);
+        assert_eq!(touched.hash, crawled.hash);
+        assert_eq!(packages_of(&index, "Foo"), vec!["a"]);
+    }
+
+    #[test]
+    fn renamed_type_leaves_the_index() {
+        let db = temporary_db();
+        let index = Index::new(&db, "proj");
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_source(dir.path(), "Foo.java", "package a; class Foo {}");
+        reindex_project_file(&index, &path, None).unwrap();
+        reindex_project_file(&index, &path, Some("package a; class Bar {}")).unwrap();
+        assert!(packages_of(&index, "Foo").is_empty());
+        assert_eq!(packages_of(&index, "Bar"), vec!["a"]);
+        assert!(index.declaring_files("Foo", "a").unwrap().is_empty());
+    }
+
+    #[test]
+    fn deleted_project_file_takes_only_its_own_types() {
+        let db = temporary_db();
+        let index = Index::new(&db, "proj");
+        let dir = tempfile::tempdir().unwrap();
+        let foo = write_source(
+            dir.path(),
+            "Foo.java",
+            "package a; class Foo {} class Shared {}",
+        );
+        write_source(dir.path(), "Other.java", "package a; class Shared {}");
+        reindex_project_path(&index, dir.path()).unwrap();
+
+        fs::remove_file(&foo).unwrap();
+        reindex_project_file(&index, &foo, None).unwrap();
+        assert!(packages_of(&index, "Foo").is_empty());
+        assert_eq!(packages_of(&index, "Shared"), vec!["a"]);
+        assert!(index.project_file(foo.to_str().unwrap()).unwrap().is_none());
+    }
+
+    #[test]
+    fn project_file_left_out_of_the_crawl_is_removed() {
+        let db = temporary_db();
+        let index = Index::new(&db, "proj");
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_source(dir.path(), "Foo.java", "package a; class Foo {}");
+        reindex_project_path(&index, dir.path()).unwrap();
+        assert_eq!(packages_of(&index, "Foo"), vec!["a"]);
+
+        fs::write(dir.path().join(".gitignore"), "Foo.java\n").unwrap();
+        reindex_project_file(&index, &path, None).unwrap();
+        assert!(packages_of(&index, "Foo").is_empty());
+        reindex_project_file(&index, &path, Some("package a; class Foo {}")).unwrap();
+        assert!(packages_of(&index, "Foo").is_empty());
+    }
+
+    #[test]
+    fn unreadable_jar_is_named_after_the_others_are_stored() {
+        let db = temporary_db();
+        let index = Index::new(&db, "jars");
+        let dir = tempfile::tempdir().unwrap();
+        write_jar(&dir.path().join("good.jar"), &["com/example/Widget.class"]);
+        fs::write(dir.path().join("broken.jar"), b"not a zip").unwrap();
+
+        let error = reindex_jar_dir(&index, dir.path()).unwrap_err().to_string();
+        assert!(error.starts_with("1 of 2 classpath elements"), "{}", error);
+        assert!(error.contains("broken.jar"), "{}", error);
+        assert_eq!(packages_of(&index, "Widget"), vec!["com.example"]);
+    }
+
+    #[test]
+    fn build_module_visibility_follows_dependencies() {
+        let db = temporary_db();
+        let index = Index::new(&db, "proj");
+        let dir = tempfile::tempdir().unwrap();
+        let root = fs::canonicalize(dir.path()).unwrap();
+        for name in ["app", "lib", "core", "other"] {
+            fs::create_dir(root.join(name)).unwrap();
+        }
+        let module = |name: &str, dependencies: &[&str]| BuildModule {
+            dir: root.join(name),
+            name: name.to_string(),
+            dependencies: dependencies.iter().map(|dep| root.join(dep)).collect(),
+        };
+        let modules = vec![
+            module("app", &["lib"]),
+            module("lib", &["core"]),
+            module("core", &["lib"]),
+            module("other", &[]),
+        ];
+        index.store_build_modules(&root, &modules).unwrap();
+
+        let visible = index
+            .visible_build_modules(&root.join("other/../app/src/Main.java"))
+            .unwrap()
+            .unwrap();
+        let expected: HashSet<String> = ["app", "lib", "core"]
+            .iter()
+            .map(|name| root.join(name).to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(visible, expected);
+        assert!(index
+            .visible_build_modules(&root.join("Outside.java"))
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn scopes_are_visible_as_on_maven_classpaths() {
+        let visible = |scope: Scope| {
+            [Scope::Main, Scope::Test, Scope::Compile, Scope::Runtime]
+                .into_iter()
+                .filter(|query_scope| scope.visible_from(*query_scope))
+                .collect::<Vec<Scope>>()
+        };
+        assert_eq!(
+            visible(Scope::Main),
+            vec![Scope::Main, Scope::Test, Scope::Compile, Scope::Runtime]
+        );
+        assert_eq!(
+            visible(Scope::Compile),
+            vec![Scope::Main, Scope::Test, Scope::Compile, Scope::Runtime]
+        );
+        assert_eq!(visible(Scope::Runtime), vec![Scope::Test, Scope::Runtime]);
+        assert_eq!(visible(Scope::Test), vec![Scope::Test]);
+    }
+
+    #[test]
+    fn test_dependencies_are_not_offered_to_main_code() {
+        let db = temporary_db();
+        let index = Index::new(&db, "deps");
+        let dir = tempfile::tempdir().unwrap();
+        let junit = dir.path().join("junit.jar");
+        write_jar(&junit, &["org/junit/Test.class", "org/junit/Assert.class"]);
+        reindex_classpath(&index, junit.to_str().unwrap(), Some(Scope::Test)).unwrap();
+
+        let main_file = dir.path().join("app/src/main/java/com/example/App.java");
+        let filter = index.query_filter(false, Some(&main_file), None).unwrap();
+        assert_eq!(filter.scope, Some(Scope::Main));
+        let results = index.query_class_index_with("Test", &filter).unwrap();
+        assert!(results["Test"].is_empty());
+
+        let test_file = dir
+            .path()
+            .join("app/src/test/java/com/example/AppTest.java");
+        let filter = index.query_filter(false, Some(&test_file), None).unwrap();
+        let results = index.query_class_index_with("Assert", &filter).unwrap();
+        assert_eq!(results["Assert"], vec!["org.junit"]);
+    }
+
+    #[test]
+    fn an_untagged_provider_keeps_a_type_visible() {
+        let db = temporary_db();
+        let index = Index::new(&db, "deps");
+        let dir = tempfile::tempdir().unwrap();
+        let untagged = dir.path().join("annotations.jar");
+        let tagged = dir.path().join("annotations-test.jar");
+        write_jar(&untagged, &["org/example/Nullable.class"]);
+        write_jar(&tagged, &["org/example/Nullable.class"]);
+        reindex_classpath(&index, tagged.to_str().unwrap(), Some(Scope::Test)).unwrap();
+        assert!(packages_from(&index, "Nullable", Scope::Main).is_empty());
+
+        reindex_classpath(&index, untagged.to_str().unwrap(), None).unwrap();
+        assert_eq!(
+            packages_from(&index, "Nullable", Scope::Main),
+            vec!["org.example"]
+        );
+    }
+
+    #[test]
+    fn reindexing_a_dependency_replaces_its_scope() {
+        let db = temporary_db();
+        let index = Index::new(&db, "deps");
+        let dir = tempfile::tempdir().unwrap();
+        let jar = dir.path().join("lib.jar");
+        write_jar(&jar, &["org/example/Lib.class"]);
+        let class_path = jar.to_str().unwrap();
+
+        reindex_classpath(&index, class_path, Some(Scope::Test)).unwrap();
+        assert!(packages_from(&index, "Lib", Scope::Main).is_empty());
+        reindex_classpath(&index, class_path, Some(Scope::Compile)).unwrap();
+        assert_eq!(
+            packages_from(&index, "Lib", Scope::Main),
+            vec!["org.example"]
+        );
+        reindex_classpath(&index, class_path, Some(Scope::Test)).unwrap();
+        assert!(packages_from(&index, "Lib", Scope::Main).is_empty());
+        reindex_classpath(&index, class_path, None).unwrap();
+        assert_eq!(
+            packages_from(&index, "Lib", Scope::Main),
+            vec!["org.example"]
+        );
+    }
+
+    #[test]
+    fn moving_a_project_type_to_main_sources_retags_it() {
+        let db = temporary_db();
+        let index = Index::new(&db, "proj");
+        let dir = tempfile::tempdir().unwrap();
+        let test_dir = dir.path().join("src/test/java/a");
+        let main_dir = dir.path().join("src/main/java/a");
+        fs::create_dir_all(&test_dir).unwrap();
+        fs::create_dir_all(&main_dir).unwrap();
+        let test_file = write_source(&test_dir, "Fixture.java", "package a; class Fixture {}");
+        reindex_project_file(&index, &test_file, None).unwrap();
+        assert!(packages_from(&index, "Fixture", Scope::Main).is_empty());
+        assert_eq!(packages_from(&index, "Fixture", Scope::Test), vec!["a"]);
+
+        fs::remove_file(&test_file).unwrap();
+        reindex_project_file(&index, &test_file, None).unwrap();
+        let main_file = write_source(&main_dir, "Fixture.java", "package a; class Fixture {}");
+        reindex_project_file(&index, &main_file, None).unwrap();
+        assert_eq!(packages_from(&index, "Fixture", Scope::Main), vec!["a"]);
+    }
+
+    #[test]
+    fn dependency_type_survives_deleting_a_source_declaring_it() {
+        let db = temporary_db();
+        let dir = tempfile::tempdir().unwrap();
+        let root = fs::canonicalize(dir.path()).unwrap().join("app");
+        let src_dir = root.join("src/main/java/com/example");
+        fs::create_dir_all(&src_dir).unwrap();
+        fs::create_dir_all(root.join("lib")).unwrap();
+        fs::write(
+            root.join(".cpid.toml"),
+            "[classpath]\ncompile = [\"lib/*\"]\n",
+        )
+        .unwrap();
+        write_jar(&root.join("lib/dep.jar"), &["com/example/Shared.class"]);
+        let source = write_source(
+            &src_dir,
+            "Shared.java",
+            "package com.example; class Shared {}",
+        );
+
+        let project = ConfiguredProject::discover(&root).unwrap();
+        assert_eq!(project.query_indexes(), vec!["app", "app-deps"]);
+        reindex_configured_project(&db, &project, None).unwrap();
+        let sources = Index::new(&db, "app");
+        let dependencies = Index::new(&db, "app-deps");
+        assert_eq!(packages_of(&sources, "Shared"), vec!["com.example"]);
+
+        fs::remove_file(&source).unwrap();
+        reindex_configured_project(&db, &project, None).unwrap();
+        assert!(packages_of(&sources, "Shared").is_empty());
+        assert_eq!(packages_of(&dependencies, "Shared"), vec!["com.example"]);
+    }
+
+    #[test]
+    fn configured_project_needs_separate_indexes() {
+        let db = temporary_db();
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join(".cpid.toml"),
+            "[indexes]\nsources = \"app\"\ndependencies = \"app\"\n",
+        )
+        .unwrap();
+        let project = ConfiguredProject::discover(dir.path()).unwrap();
+        let error = reindex_configured_project(&db, &project, None).unwrap_err();
+        assert!(
+            error.to_string().contains("sources index, app"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn project_crawl_reports_each_parsed_file() {
+        let db = temporary_db();
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["A", "B", "C"] {
+            write_source(
+                dir.path(),
+                &format!("{}.java", name),
+                &format!("package a; class {} {{}}", name),
+            );
+        }
+        let reports: std::sync::Arc<std::sync::Mutex<Vec<ReindexProgress>>> =
+            std::sync::Arc::default();
+        let progress = {
+            let reports = reports.clone();
+            move |p: &ReindexProgress| reports.lock().unwrap().push(p.clone())
+        };
+        let index = Index::new(&db, "proj").with_progress(&progress);
+
+        reindex_project_path(&index, dir.path()).unwrap();
+        let done: Vec<(usize, usize)> = reports
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|p| (p.archives_done, p.archives_total))
+            .collect();
+        assert_eq!(done, vec![(1, 3), (2, 3), (3, 3)]);
+    }
+
+    #[test]
+    fn coordinates_key_default_package_classes_by_their_name() {
+        let batch = IndexBatch {
+            classes: vec![
+                ("Foo".to_string(), String::new(), "Foo.class".to_string()),
+                (
+                    "Bar".to_string(),
+                    "org.example".to_string(),
+                    "org/example/Bar.class".to_string(),
+                ),
+            ],
+            ..IndexBatch::default()
+        };
+        let coordinate = Coordinate::parse("org.example:lib:1.0").unwrap();
+        let keys: Vec<String> = batch
+            .with_coordinate(&coordinate)
+            .coordinates
+            .into_iter()
+            .map(|(fqn, _)| fqn)
+            .collect();
+        assert_eq!(keys, vec!["Foo", "org.example.Bar"]);
+    }
+
+    #[test]
+    fn class_dir_packages_follow_its_subdirectories() {
+        let dir = tempfile::tempdir().unwrap();
+        let classes = fixture("classes");
+        for (fixture_name, dest) in [
+            ("Widget.class", "com/example/api/Widget.class"),
+            ("module-info.class", "module-info.class"),
+            ("jvmlang/Widget.class", "app/Widget.class"),
+            ("jvmlang/Registry$.class", "app/Registry$.class"),
+        ] {
+            let dest = dir.path().join(dest);
+            fs::create_dir_all(dest.parent().unwrap()).unwrap();
+            fs::copy(classes.join(fixture_name), dest).unwrap();
+        }
+        fs::write(dir.path().join("app/messages.properties"), "").unwrap();
+
+        let mut tuples = index_class_dir(dir.path(), &IndexSettings::default()).unwrap();
+        tuples.sort();
+        assert_eq!(
+            tuples,
+            vec![
+                (
+                    "Widget".to_string(),
+                    "app".to_string(),
+                    "app/Widget.class".to_string()
+                ),
+                (
+                    "Widget".to_string(),
+                    "com.example.api".to_string(),
+                    "com/example/api/Widget.class".to_string()
+                ),
+            ]
+        );
+    }
+
+    fn sorted_tuples(path: &Path) -> Vec<(String, String, String)> {
+        let mut tuples = index_zip_archive(path, &IndexSettings::default()).unwrap();
+        tuples.sort();
+        tuples
+    }
+
+    fn tuple(class_name: &str, package_name: &str, entry: &str) -> (String, String, String) {
+        (
+            class_name.to_string(),
+            package_name.to_string(),
+            entry.to_string(),
+        )
+    }
+
+    #[test]
+    fn fat_jar_classes_and_nested_jars_are_indexed() {
+        assert_eq!(
+            sorted_tuples(&fixture("boot-app.jar")),
+            vec![
+                tuple(
+                    "App",
+                    "com.example",
+                    "BOOT-INF/classes/com/example/App.class"
+                ),
+                tuple(
+                    "Dep",
+                    "org.dep",
+                    "BOOT-INF/lib/dep-1.0.jar!/org/dep/Dep.class"
+                ),
+                tuple(
+                    "JarLauncher",
+                    "org.springframework.boot.loader",
+                    "org/springframework/boot/loader/JarLauncher.class"
+                ),
+                tuple(
+                    "Strings",
+                    "org.dep.util",
+                    "BOOT-INF/lib/dep-1.0.jar!/org/dep/util/Strings.class"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn war_classes_and_libraries_are_indexed() {
+        assert_eq!(
+            sorted_tuples(&fixture("webapp.war")),
+            vec![
+                tuple(
+                    "Dep",
+                    "org.dep",
+                    "WEB-INF/lib/dep-1.0.jar!/org/dep/Dep.class"
+                ),
+                tuple(
+                    "Servlet",
+                    "com.example.web",
+                    "WEB-INF/classes/com/example/web/Servlet.class"
+                ),
+                tuple(
+                    "Strings",
+                    "org.dep.util",
+                    "WEB-INF/lib/dep-1.0.jar!/org/dep/util/Strings.class"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn versioned_entry_splits_off_the_release() {
+        assert_eq!(
+            versioned_entry("META-INF/versions/11/com/example/Api.class"),
+            Some((11, "com/example/Api.class"))
+        );
+        assert_eq!(versioned_entry("META-INF/versions/x/Api.class"), None);
+        assert_eq!(versioned_entry("META-INF/versions/11"), None);
+        assert_eq!(versioned_entry("com/example/Api.class"), None);
+    }
+
+    #[test]
+    fn multi_release_entries_fold_up_to_the_target_release() {
+        let jar = fixture("multi-release.jar");
+        let indexed = |target_release: Option<u32>| {
+            let settings = IndexSettings {
+                target_release,
+                ..IndexSettings::default()
+            };
+            let mut tuples = index_zip_archive(&jar, &settings).unwrap();
+            tuples.sort();
+            tuples
+        };
+        assert_eq!(
+            indexed(Some(11)),
+            vec![
+                tuple(
+                    "Api",
+                    "com.example",
+                    "META-INF/versions/11/com/example/Api.class"
+                ),
+                tuple("Base", "com.example", "com/example/Base.class"),
+                tuple(
+                    "Java11Only",
+                    "com.example",
+                    "META-INF/versions/11/com/example/Java11Only.class"
+                ),
+            ]
+        );
+        assert_eq!(
+            indexed(Some(8)),
+            vec![
+                tuple("Api", "com.example", "com/example/Api.class"),
+                tuple("Base", "com.example", "com/example/Base.class"),
+            ]
+        );
+        let newest = indexed(None);
+        assert!(newest.contains(&tuple(
+            "Api",
+            "com.example",
+            "META-INF/versions/17/com/example/Api.class"
+        )));
+        assert!(newest
+            .iter()
+            .any(|(class_name, _, _)| class_name == "Modern"));
+        assert_eq!(newest.len(), 4);
+    }
+
+    #[test]
+    fn jmod_classes_and_module_are_indexed() {
+        let (mut tuples, descriptor) = index_jmod(&fixture("com.example.app.jmod")).unwrap();
+        tuples.sort();
+        assert_eq!(
+            tuples,
+            vec![
+                tuple(
+                    "Impl",
+                    "com.example.internal",
+                    "classes/com/example/internal/Impl.class"
+                ),
+                tuple(
+                    "Widget",
+                    "com.example.api",
+                    "classes/com/example/api/Widget.class"
+                ),
+            ]
+        );
+        assert_eq!(descriptor.unwrap().name, "com.example.app");
+        assert!(index_jmod(&fixture("boot-app.jar")).is_err());
+    }
+
+    #[test]
+    fn ct_sym_entries_are_read_for_one_release() {
+        let ct_sym = fixture("ct.sym");
+        let names = |release: u32| {
+            let mut names: Vec<String> = index_ct_sym(&ct_sym, release)
+                .unwrap()
+                .into_iter()
+                .map(|(class_name, package_name, _)| format!("{}.{}", package_name, class_name))
+                .collect();
+            names.sort();
+            names
+        };
+        assert_eq!(names(9), vec!["java.lang.Compiler", "java.lang.Object"]);
+        assert_eq!(
+            names(12),
+            vec![
+                "java.lang.Object",
+                "java.lang.Record",
+                "java.util.Spliterator2"
+            ]
+        );
+    }
+
+    #[test]
+    fn ct_sym_without_the_release_names_those_it_has() {
+        let error = index_ct_sym(&fixture("ct.sym"), 17).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .ends_with("has no entries for release 17. It holds releases 9, 10, 11, 12."),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn aar_classes_jar_and_bundled_libs_are_indexed() {
+        assert_eq!(
+            sorted_tuples(&fixture("widgets.aar")),
+            vec![
+                tuple("Buffer", "okio", "libs/okio-1.0.jar!/okio/Buffer.class"),
+                tuple(
+                    "Widget",
+                    "com.example.ui",
+                    "classes.jar!/com/example/ui/Widget.class"
+                ),
+            ]
+        );
+    }
+}
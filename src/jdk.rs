@@ -1,7 +1,13 @@
 #![allow(unused_imports)]
 #![allow(unused_variables)]
 
+use std::collections::HashSet;
+use std::fs;
 use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Every .jmod file starts with this header, followed by an ordinary zip archive.
+pub const JMOD_HEADER: [u8; 4] = [b'J', b'M', 1, 0];
 
 pub fn is_jimage_file(path: &str) -> bool {
     let mut buf = [0; 4];
@@ -10,3 +16,212 @@ pub fn is_jimage_file(path: &str) -> bool {
         .map(|()| buf[0] == 0xda && buf[1] == 0xda && buf[2] == 0xfe && buf[3] == 0xca)
         .unwrap_or(false)
 }
+
+pub fn is_jmod_file(path: &Path) -> bool {
+    let mut buf = [0; 4];
+    std::fs::File::open(path)
+        .and_then(|mut inf| inf.read_exact(&mut buf))
+        .map(|()| buf == JMOD_HEADER)
+        .unwrap_or(false)
+}
+
+pub fn java_home_from_env() -> Option<PathBuf> {
+    std::env::var_os("JAVA_HOME").map(PathBuf::from)
+}
+
+/// ct.sym names its top-level directories after the releases whose API they hold, one base-36
+/// digit per release ("8", "9ABC", ...), the way javac encodes them.
+pub fn ct_sym_release_char(release: u32) -> Option<char> {
+    std::char::from_digit(release, 36).map(|c| c.to_ascii_uppercase())
+}
+
+/// A JDK found on this machine. `major` is the feature release, so 8 for a "1.8.0_392" JDK.
+#[derive(Clone, Debug, PartialEq)]
+pub struct JdkInstall {
+    pub home: PathBuf,
+    pub version: String,
+    pub major: u32,
+}
+
+impl JdkInstall {
+    /// The index a JDK is stored under when discovered, e.g. `jdk21`.
+    pub fn index_name(&self) -> String {
+        format!("jdk{}", self.major)
+    }
+
+    /// The modules image of a JDK 9+ install.
+    pub fn jimage_path(&self) -> Option<PathBuf> {
+        Some(self.home.join("lib").join("modules")).filter(|p| p.is_file())
+    }
+
+    /// The class library of a JDK 8 install, which predates the modules image.
+    pub fn rt_jar_path(&self) -> Option<PathBuf> {
+        Some(self.home.join("jre").join("lib").join("rt.jar")).filter(|p| p.is_file())
+    }
+
+    /// JDK 9+ keeps src.zip under lib/, JDK 8 at the top of the install.
+    pub fn src_zip_path(&self) -> Option<PathBuf> {
+        [
+            self.home.join("lib").join("src.zip"),
+            self.home.join("src.zip"),
+        ]
+        .into_iter()
+        .find(|p| p.is_file())
+    }
+}
+
+fn parse_major_version(version: &str) -> Option<u32> {
+    let mut parts = version.split(|c: char| !c.is_ascii_digit());
+    match parts.next()?.parse::<u32>().ok()? {
+        1 => parts.next()?.parse().ok(),
+        major => Some(major),
+    }
+}
+
+/// Reads `JAVA_VERSION` from the `release` file every JDK since 7 has at its top level.
+pub fn read_jdk_install(home: &Path) -> Option<JdkInstall> {
+    let release = fs::read_to_string(home.join("release")).ok()?;
+    let version = release
+        .lines()
+        .find_map(|ln| ln.strip_prefix("JAVA_VERSION="))
+        .map(|v| v.trim().trim_matches('"').to_string())?;
+    let major = parse_major_version(&version)?;
+    Some(JdkInstall {
+        home: home.to_path_buf(),
+        version,
+        major,
+    })
+}
+
+fn subdirectories(dir: &Path) -> Vec<PathBuf> {
+    let mut dirs = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| p.is_dir())
+                .collect::<Vec<PathBuf>>()
+        })
+        .unwrap_or_default();
+    dirs.sort();
+    dirs
+}
+
+/// Finds installed JDKs in `JAVA_HOME`, `/usr/lib/jvm`, SDKMAN's and asdf's install directories,
+/// in that order. Installs reachable through several paths (Debian's `default-java` symlink,
+/// SDKMAN's `current`) are reported once.
+pub fn discover_jdks() -> Vec<JdkInstall> {
+    let mut candidates: Vec<PathBuf> = java_home_from_env().into_iter().collect();
+    candidates.extend(subdirectories(Path::new("/usr/lib/jvm")));
+    if let Some(home) = std::env::var_os("HOME").map(PathBuf::from) {
+        let sdkman_dir = std::env::var_os("SDKMAN_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| home.join(".sdkman"));
+        candidates.extend(subdirectories(&sdkman_dir.join("candidates").join("java")));
+        let asdf_dir = std::env::var_os("ASDF_DATA_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| home.join(".asdf"));
+        candidates.extend(subdirectories(&asdf_dir.join("installs").join("java")));
+    }
+
+    let mut seen: HashSet<PathBuf> = HashSet::new();
+    candidates
+        .into_iter()
+        .filter(|home| seen.insert(fs::canonicalize(home).unwrap_or_else(|_| home.clone())))
+        .filter_map(|home| read_jdk_install(&home))
+        .collect()
+}
+
+/// Prefers the JDK's own `jimage` tool, since the image format is tied to the JDK that wrote it,
+/// and falls back to whichever one is on the `PATH`.
+pub fn jimage_command_for(image_path: &Path) -> PathBuf {
+    image_path
+        .parent()
+        .and_then(Path::parent)
+        .map(|home| home.join("bin").join("jimage"))
+        .filter(|cmd| cmd.is_file())
+        .unwrap_or_else(|| PathBuf::from("jimage"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ct_sym_release_char_is_base_36() {
+        assert_eq!(ct_sym_release_char(8), Some('8'));
+        assert_eq!(ct_sym_release_char(10), Some('A'));
+        assert_eq!(ct_sym_release_char(17), Some('H'));
+        assert_eq!(ct_sym_release_char(35), Some('Z'));
+        assert_eq!(ct_sym_release_char(36), None);
+    }
+
+    #[test]
+    fn parse_major_version_reads_legacy_and_current_schemes() {
+        assert_eq!(parse_major_version("1.8.0_292"), Some(8));
+        assert_eq!(parse_major_version("17.0.2"), Some(17));
+        assert_eq!(parse_major_version("21"), Some(21));
+        assert_eq!(parse_major_version("openjdk"), None);
+    }
+
+    fn write_release(home: &Path, version: &str) {
+        fs::create_dir_all(home).unwrap();
+        fs::write(
+            home.join("release"),
+            format!("IMPLEMENTOR=\"Eclipse Adoptium\"\nJAVA_VERSION=\"{version}\"\n"),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn read_jdk_install_parses_the_release_file() {
+        let dir = tempfile::tempdir().unwrap();
+        write_release(dir.path(), "1.8.0_292");
+        assert_eq!(
+            read_jdk_install(dir.path()),
+            Some(JdkInstall {
+                home: dir.path().to_path_buf(),
+                version: "1.8.0_292".to_string(),
+                major: 8,
+            })
+        );
+        assert_eq!(read_jdk_install(dir.path()).unwrap().index_name(), "jdk8");
+
+        fs::write(dir.path().join("release"), "IMPLEMENTOR=\"Oracle\"\n").unwrap();
+        assert_eq!(read_jdk_install(dir.path()), None);
+        assert_eq!(read_jdk_install(&dir.path().join("missing")), None);
+    }
+
+    #[test]
+    fn discover_jdks_searches_java_home_sdkman_and_asdf() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = fs::canonicalize(dir.path()).unwrap();
+        let java_home = root.join("java-home");
+        write_release(&java_home, "21.0.1");
+        let sdkman_java = root.join("sdkman").join("candidates").join("java");
+        write_release(&sdkman_java.join("17.0.2-tem"), "17.0.2");
+        std::os::unix::fs::symlink(sdkman_java.join("17.0.2-tem"), sdkman_java.join("current"))
+            .unwrap();
+        let asdf_java = root.join("asdf").join("installs").join("java");
+        write_release(&asdf_java.join("adoptopenjdk-8"), "1.8.0_292");
+        fs::create_dir_all(asdf_java.join("not-a-jdk")).unwrap();
+
+        std::env::set_var("JAVA_HOME", &java_home);
+        std::env::set_var("HOME", root.join("home"));
+        std::env::set_var("SDKMAN_DIR", root.join("sdkman"));
+        std::env::set_var("ASDF_DATA_DIR", root.join("asdf"));
+        let found: Vec<(PathBuf, u32)> = discover_jdks()
+            .into_iter()
+            .filter(|jdk| jdk.home.starts_with(&root))
+            .map(|jdk| (jdk.home, jdk.major))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (java_home, 21),
+                (sdkman_java.join("17.0.2-tem"), 17),
+                (asdf_java.join("adoptopenjdk-8"), 8),
+            ]
+        );
+    }
+}
@@ -0,0 +1,265 @@
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+
+use std::collections::{BTreeMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::time::Instant;
+
+use anyhow::Result;
+use serde_derive::Serialize;
+
+use crate::indexes::ReindexProgress;
+
+/// How many jobs that are over are kept for status queries. Older ones are forgotten first.
+const MAX_ENDED_JOBS: usize = 100;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub enum JobState {
+    Running,
+    Finished,
+    Failed,
+}
+
+/// A snapshot of a background job, as sent to clients.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct JobStatus {
+    pub job_id: u64,
+    /// The message type that started the job, such as `ReindexClasspathCmd`.
+    pub command: String,
+    pub state: JobState,
+    #[serde(flatten)]
+    pub progress: ReindexProgress,
+    /// How long the job has run, or ran for once it is over.
+    pub elapsed_secs: f64,
+    /// Why the job failed, with the causes of the failure.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+struct Job {
+    command: String,
+    state: JobState,
+    progress: ReindexProgress,
+    started: Instant,
+    ended: Option<Instant>,
+    error: Option<String>,
+}
+
+impl Job {
+    fn status(&self, job_id: u64) -> JobStatus {
+        let elapsed = self.ended.unwrap_or_else(Instant::now) - self.started;
+        JobStatus {
+            job_id,
+            command: self.command.clone(),
+            state: self.state,
+            progress: self.progress.clone(),
+            elapsed_secs: elapsed.as_secs_f64(),
+            error: self.error.clone(),
+        }
+    }
+}
+
+/// The background jobs of a server, shared by all of its connections so that any of them can ask
+/// after a job another started. The last `MAX_ENDED_JOBS` jobs that are over are kept for later
+/// status queries.
+///
+/// The registry also serializes writes to each index: whatever updates an index holds a lease on
+/// it for as long as it runs, so that two reindexes never interleave their updates.
+#[derive(Default)]
+pub struct JobRegistry {
+    next_id: AtomicU64,
+    jobs: Mutex<BTreeMap<u64, Job>>,
+    busy_indexes: Mutex<HashSet<String>>,
+    index_released: Condvar,
+}
+
+/// Exclusive use of some indexes, given up when dropped.
+pub struct IndexLease<'a> {
+    registry: &'a JobRegistry,
+    index_names: Vec<String>,
+}
+
+impl Drop for IndexLease<'_> {
+    fn drop(&mut self) {
+        let mut busy = self.registry.busy_indexes.lock().unwrap();
+        for index_name in self.index_names.iter() {
+            busy.remove(index_name);
+        }
+        self.registry.index_released.notify_all();
+    }
+}
+
+impl JobRegistry {
+    /// Records a new running job and returns its id. Ids start at 1.
+    pub fn start(&self, command: &str) -> u64 {
+        let job_id = self.next_id.fetch_add(1, Ordering::SeqCst) + 1;
+        self.jobs.lock().unwrap().insert(
+            job_id,
+            Job {
+                command: command.to_string(),
+                state: JobState::Running,
+                progress: ReindexProgress::default(),
+                started: Instant::now(),
+                ended: None,
+                error: None,
+            },
+        );
+        job_id
+    }
+
+    pub fn update_progress(&self, job_id: u64, progress: &ReindexProgress) -> Option<JobStatus> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let job = jobs.get_mut(&job_id)?;
+        job.progress = progress.clone();
+        Some(job.status(job_id))
+    }
+
+    /// Marks a job as over with its result, returning its final status.
+    pub fn finish(&self, job_id: u64, result: &Result<()>) -> Option<JobStatus> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let job = jobs.get_mut(&job_id)?;
+        job.ended = Some(Instant::now());
+        match result {
+            Ok(()) => job.state = JobState::Finished,
+            Err(e) => {
+                job.state = JobState::Failed;
+                job.error = Some(format!("{:#}", e));
+            }
+        }
+        let status = job.status(job_id);
+
+        let ended: Vec<u64> = jobs
+            .iter()
+            .filter(|(_, job)| job.ended.is_some())
+            .map(|(job_id, _)| *job_id)
+            .collect();
+        for job_id in ended
+            .iter()
+            .take(ended.len().saturating_sub(MAX_ENDED_JOBS))
+        {
+            jobs.remove(job_id);
+        }
+        Some(status)
+    }
+
+    /// Waits until none of the named indexes are in use, then takes them all at once. Taking
+    /// them together rather than one by one means two callers can never each hold an index the
+    /// other waits on.
+    pub fn lease_indexes(&self, index_names: &[String]) -> IndexLease<'_> {
+        let mut busy = self.busy_indexes.lock().unwrap();
+        while index_names.iter().any(|name| busy.contains(name)) {
+            busy = self.index_released.wait(busy).unwrap();
+        }
+        busy.extend(index_names.iter().cloned());
+        IndexLease {
+            registry: self,
+            index_names: index_names.to_vec(),
+        }
+    }
+
+    /// Takes the named indexes if none of them are in use, without waiting.
+    pub fn try_lease_indexes(&self, index_names: &[String]) -> Option<IndexLease<'_>> {
+        let mut busy = self.busy_indexes.lock().unwrap();
+        if index_names.iter().any(|name| busy.contains(name)) {
+            return None;
+        }
+        busy.extend(index_names.iter().cloned());
+        Some(IndexLease {
+            registry: self,
+            index_names: index_names.to_vec(),
+        })
+    }
+
+    pub fn status(&self, job_id: u64) -> Option<JobStatus> {
+        let jobs = self.jobs.lock().unwrap();
+        jobs.get(&job_id).map(|job| job.status(job_id))
+    }
+
+    pub fn statuses(&self) -> Vec<JobStatus> {
+        let jobs = self.jobs.lock().unwrap();
+        jobs.iter()
+            .map(|(job_id, job)| job.status(*job_id))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn only_the_latest_ended_jobs_are_kept() {
+        let registry = JobRegistry::default();
+        let running = registry.start("ReindexPathCmd");
+        for _ in 0..MAX_ENDED_JOBS + 5 {
+            let job_id = registry.start("ReindexClasspathCmd");
+            registry.finish(job_id, &Ok(()));
+        }
+        let statuses = registry.statuses();
+        assert_eq!(statuses.len(), MAX_ENDED_JOBS + 1);
+        assert_eq!(statuses[0].job_id, running);
+        assert_eq!(statuses[0].state, JobState::Running);
+        assert!(registry.status(running + 1).is_none());
+        assert!(registry.status(running + 6).is_some());
+    }
+
+    #[test]
+    fn failed_job_keeps_its_error_and_causes() {
+        let registry = JobRegistry::default();
+        let job_id = registry.start("ReindexPomCmd");
+        let result: Result<()> = Err(anyhow::anyhow!("missing jar").context("Could not index"));
+        let status = registry.finish(job_id, &result).unwrap();
+        assert_eq!(status.state, JobState::Failed);
+        assert_eq!(
+            status.error.as_deref(),
+            Some("Could not index: missing jar")
+        );
+    }
+
+    #[test]
+    fn a_leased_index_waits_for_its_holder() {
+        let registry = Arc::new(JobRegistry::default());
+        let names = vec!["app".to_string(), "app-deps".to_string()];
+        let lease = registry.lease_indexes(&names);
+        let taken = Arc::new(AtomicBool::new(false));
+
+        let waiter = {
+            let (registry, taken) = (registry.clone(), taken.clone());
+            thread::spawn(move || {
+                let _lease = registry.lease_indexes(&["app-deps".to_string()]);
+                taken.store(true, Ordering::SeqCst);
+            })
+        };
+        // An index nobody holds is free at once.
+        drop(registry.lease_indexes(&["jdk17".to_string()]));
+        thread::sleep(Duration::from_millis(100));
+        assert!(!taken.load(Ordering::SeqCst));
+
+        drop(lease);
+        waiter.join().unwrap();
+        assert!(taken.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn trying_a_leased_index_does_not_wait() {
+        let registry = JobRegistry::default();
+        let lease = registry.lease_indexes(&["app".to_string()]);
+        assert!(registry
+            .try_lease_indexes(&["app".to_string(), "app-deps".to_string()])
+            .is_none());
+        // A failed attempt takes nothing.
+        drop(
+            registry
+                .try_lease_indexes(&["app-deps".to_string()])
+                .unwrap(),
+        );
+
+        drop(lease);
+        assert!(registry.try_lease_indexes(&["app".to_string()]).is_some());
+    }
+}
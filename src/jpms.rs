@@ -0,0 +1,162 @@
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+
+use std::collections::HashSet;
+use std::fs;
+use std::io::{Read, Seek};
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use zip::read::ZipArchive;
+
+use crate::classfile::{ByteReader, ClassFile};
+use crate::manifest::Manifest;
+
+pub const MODULE_INFO_CLASS: &str = "module-info.class";
+
+/// What the index needs to know about a JPMS module: its name and the packages it exports to
+/// every other module. Packages exported only to named friends (`exports a.b to c.d`) count as
+/// not exported.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ModuleDescriptor {
+    pub name: String,
+    /// `None` for automatic modules, which export every package.
+    pub exports: Option<HashSet<String>>,
+}
+
+impl ModuleDescriptor {
+    pub fn automatic(name: &str) -> Self {
+        ModuleDescriptor {
+            name: name.to_string(),
+            exports: None,
+        }
+    }
+
+    /// Reads the `Module` attribute of a compiled module-info.class.
+    pub fn from_class_bytes(bytes: &[u8]) -> Result<Self> {
+        let class_file = ClassFile::parse(bytes)?;
+        let attribute = class_file
+            .attribute("Module")
+            .ok_or_else(|| anyhow!("module-info.class has no Module attribute."))?;
+
+        let mut reader = ByteReader::new(&attribute.data);
+        let name = class_file
+            .name_of(reader.u16()?)
+            .ok_or_else(|| anyhow!("Module attribute has no module name."))?
+            .to_string();
+        let _flags = reader.u16()?;
+        let _version = reader.u16()?;
+
+        let requires_count = reader.u16()? as usize;
+        reader.take(requires_count * 6)?;
+
+        let mut exports: HashSet<String> = HashSet::new();
+        for _ in 0..reader.u16()? {
+            let package_idx = reader.u16()?;
+            let _flags = reader.u16()?;
+            let to_count = reader.u16()? as usize;
+            reader.take(to_count * 2)?;
+            if to_count == 0 {
+                if let Some(package_name) = class_file.name_of(package_idx) {
+                    exports.insert(package_name.replace('/', "."));
+                }
+            }
+        }
+
+        Ok(ModuleDescriptor {
+            name,
+            exports: Some(exports),
+        })
+    }
+
+    /// Finds the module an archive declares: an explicit module-info.class, which a
+    /// multi-release jar may keep under META-INF/versions, or else an `Automatic-Module-Name`.
+    pub fn from_zip<R: Read + Seek>(archive: &mut ZipArchive<R>) -> Option<Self> {
+        let module_info_name = archive
+            .file_names()
+            .filter(|n| {
+                *n == MODULE_INFO_CLASS
+                    || (n.starts_with("META-INF/versions/") && n.ends_with(MODULE_INFO_CLASS))
+            })
+            .min_by_key(|n| n.len())
+            .map(String::from);
+
+        if let Some(module_info_name) = module_info_name {
+            let mut bytes: Vec<u8> = Vec::new();
+            let descriptor = archive
+                .by_name(&module_info_name)
+                .map_err(anyhow::Error::new)
+                .and_then(|mut entry| Ok(entry.read_to_end(&mut bytes)?))
+                .and_then(|_| ModuleDescriptor::from_class_bytes(&bytes));
+            match descriptor {
+                Ok(descriptor) => return Some(descriptor),
+                Err(e) => eprintln!("Ignoring unreadable {}: {}", module_info_name, e),
+            }
+        }
+
+        Manifest::from_zip(archive).and_then(|m| {
+            m.get("Automatic-Module-Name")
+                .map(ModuleDescriptor::automatic)
+        })
+    }
+
+    /// Finds the module an exploded class directory declares, if any.
+    pub fn from_dir(dir: &Path) -> Option<Self> {
+        let bytes = fs::read(dir.join(MODULE_INFO_CLASS)).ok()?;
+        ModuleDescriptor::from_class_bytes(&bytes)
+            .map_err(|e| {
+                eprintln!(
+                    "Ignoring unreadable module-info.class in {}: {}",
+                    dir.display(),
+                    e
+                )
+            })
+            .ok()
+    }
+
+    pub fn exports_package(&self, package_name: &str) -> bool {
+        self.exports
+            .as_ref()
+            .map(|exports| exports.contains(package_name))
+            .unwrap_or(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    #[test]
+    fn qualified_exports_count_as_not_exported() {
+        let path =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/classes/module-info.class");
+        let descriptor = ModuleDescriptor::from_class_bytes(&fs::read(path).unwrap()).unwrap();
+        assert_eq!(descriptor.name, "com.example.app");
+        assert!(descriptor.exports_package("com.example.api"));
+        assert!(!descriptor.exports_package("com.example.internal"));
+        assert!(ModuleDescriptor::automatic("x.y").exports_package("anything"));
+    }
+
+    #[test]
+    fn truncated_module_info_is_an_error() {
+        let path =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/classes/module-info.class");
+        let bytes = fs::read(path).unwrap();
+        for len in 0..bytes.len() {
+            assert!(
+                ModuleDescriptor::from_class_bytes(&bytes[..len]).is_err(),
+                "{} bytes",
+                len
+            );
+        }
+    }
+
+    #[test]
+    fn class_without_module_attribute_is_an_error() {
+        let path =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/classes/Widget.class");
+        assert!(ModuleDescriptor::from_class_bytes(&fs::read(path).unwrap()).is_err());
+    }
+}
@@ -0,0 +1,230 @@
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+
+use std::collections::HashSet;
+
+use crate::classfile::{ClassFile, ElementValue, ACC_PUBLIC, ACC_STATIC, ACC_SYNTHETIC};
+
+const KOTLIN_METADATA: &str = "Lkotlin/Metadata;";
+const SCALA_ATTRIBUTES: [&str; 3] = ["ScalaSig", "Scala", "TASTY"];
+const SCALA_MODULE_FIELD: &str = "MODULE$";
+
+/// The `k` element of `kotlin.Metadata`, saying what the compiler made the class for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum KotlinKind {
+    Class,
+    FileFacade,
+    SyntheticClass,
+    MultiFileFacade,
+    MultiFileClassPart,
+}
+
+impl KotlinKind {
+    fn from_k(k: i32) -> Option<Self> {
+        match k {
+            1 => Some(KotlinKind::Class),
+            2 => Some(KotlinKind::FileFacade),
+            3 => Some(KotlinKind::SyntheticClass),
+            4 => Some(KotlinKind::MultiFileFacade),
+            5 => Some(KotlinKind::MultiFileClassPart),
+            _ => None,
+        }
+    }
+}
+
+/// What the naming pass needs from a class's `kotlin.Metadata` annotation. The declarations
+/// themselves are protobuf-encoded in `d1`, but every name they use is listed in `d2`.
+struct KotlinMetadata {
+    kind: KotlinKind,
+    strings: HashSet<String>,
+}
+
+impl KotlinMetadata {
+    fn from_class(class_file: &ClassFile) -> Option<Self> {
+        let annotations = class_file.runtime_visible_annotations().ok()?;
+        let metadata = annotations
+            .iter()
+            .find(|a| a.type_name == KOTLIN_METADATA)?;
+
+        // `k` defaults to 1, a class, when the compiler leaves it out.
+        let kind = match metadata.element("k") {
+            Some(ElementValue::Const(b'I', idx)) => class_file.integer(*idx)?,
+            _ => 1,
+        };
+        let strings = match metadata.element("d2") {
+            Some(ElementValue::Array(values)) => values
+                .iter()
+                .filter_map(|v| match v {
+                    ElementValue::Const(b's', idx) => class_file.utf8(*idx).map(String::from),
+                    _ => None,
+                })
+                .collect(),
+            _ => HashSet::new(),
+        };
+        Some(KotlinMetadata {
+            kind: KotlinKind::from_k(kind)?,
+            strings,
+        })
+    }
+}
+
+fn is_public_static(access_flags: u16) -> bool {
+    access_flags & (ACC_PUBLIC | ACC_STATIC) == ACC_PUBLIC | ACC_STATIC
+        && access_flags & ACC_SYNTHETIC == 0
+}
+
+/// Turns a getter such as `getMaxSize` into the property name `maxSize`.
+fn property_for_getter(method_name: &str) -> Option<String> {
+    let rest = method_name.strip_prefix("get")?;
+    let mut chars = rest.chars();
+    let first = chars.next()?;
+    first
+        .is_uppercase()
+        .then(|| first.to_lowercase().chain(chars).collect())
+}
+
+/// Names the top-level functions and properties a Kotlin file facade holds. Kotlin compiles them
+/// to public static methods and fields, so those are matched against the names the metadata
+/// declares. Getters count as their property: the metadata names both, since it records the
+/// getter's JVM signature, and a function of the getter's name would clash with it anyway.
+fn kotlin_top_level_names(class_file: &ClassFile, declared: &HashSet<String>) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for method in class_file.methods.iter() {
+        if !is_public_static(method.access_flags) || method.name.contains(['$', '<']) {
+            continue;
+        }
+        match property_for_getter(&method.name) {
+            Some(property) if declared.contains(&property) => names.push(property),
+            _ if declared.contains(&method.name) => names.push(method.name.clone()),
+            _ => {}
+        }
+    }
+    for field in class_file.fields.iter() {
+        if is_public_static(field.access_flags) && declared.contains(&field.name) {
+            names.push(field.name.clone());
+        }
+    }
+    names.sort();
+    names.dedup();
+    names
+}
+
+fn kotlin_names(class_file: &ClassFile, metadata: &KotlinMetadata) -> Option<Vec<String>> {
+    let simple_name = class_file.simple_name()?;
+    match metadata.kind {
+        KotlinKind::Class if simple_name.contains('$') => Some(Vec::new()),
+        KotlinKind::Class => None,
+        KotlinKind::FileFacade => {
+            let mut names = vec![simple_name.to_string()];
+            names.extend(kotlin_top_level_names(class_file, &metadata.strings));
+            Some(names)
+        }
+        // The metadata of a multi-file facade lists its parts rather than its declarations, so
+        // every public static method it delegates to a part is taken as a top-level function.
+        KotlinKind::MultiFileFacade => {
+            let mut names = vec![simple_name.to_string()];
+            let declared = class_file
+                .methods
+                .iter()
+                .map(|m| m.name.clone())
+                .collect::<HashSet<String>>();
+            names.extend(kotlin_top_level_names(class_file, &declared));
+            Some(names)
+        }
+        KotlinKind::SyntheticClass | KotlinKind::MultiFileClassPart => Some(Vec::new()),
+    }
+}
+
+fn scala_names(class_file: &ClassFile) -> Option<Vec<String>> {
+    let simple_name = class_file.simple_name()?;
+    let is_module = class_file
+        .fields
+        .iter()
+        .any(|f| f.name == SCALA_MODULE_FIELD && f.access_flags & ACC_STATIC != 0);
+    let is_scala = is_module
+        || SCALA_ATTRIBUTES
+            .iter()
+            .any(|name| class_file.attribute(name).is_some());
+    if !is_scala {
+        return None;
+    }
+
+    match simple_name.strip_suffix('$') {
+        // Package objects hold members of the package itself rather than a type.
+        Some("package") => Some(Vec::new()),
+        Some(object_name) if is_module && !object_name.contains('$') => {
+            Some(vec![object_name.to_string()])
+        }
+        _ if simple_name.contains('$') => Some(Vec::new()),
+        _ => None,
+    }
+}
+
+/// Names a compiled class the way Kotlin or Scala source refers to it. Returns `None` for classes
+/// that either compiler left alone, which keep their usual naming. Compiler-generated classes
+/// such as `$DefaultImpls`, lambdas and multi-file parts get no names at all; Kotlin file facades
+/// add their top-level functions and properties; Scala objects drop their trailing `$`.
+pub fn source_names(class_file: &ClassFile) -> Option<Vec<String>> {
+    match KotlinMetadata::from_class(class_file) {
+        Some(metadata) => kotlin_names(class_file, &metadata),
+        None => scala_names(class_file),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use super::*;
+
+    fn names_of(fixture: &str) -> Option<Vec<String>> {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/classes")
+            .join(fixture);
+        source_names(&ClassFile::parse(&fs::read(path).unwrap()).unwrap())
+    }
+
+    #[test]
+    fn file_facade_names_its_top_level_functions_and_properties() {
+        assert_eq!(
+            names_of("jvmlang/UtilsKt.class"),
+            Some(vec![
+                "UtilsKt".to_string(),
+                "LIMIT".to_string(),
+                "maxSize".to_string(),
+                "topFun".to_string(),
+            ])
+        );
+    }
+
+    #[test]
+    fn synthetic_classes_get_no_names() {
+        assert_eq!(names_of("jvmlang/Utils$topFun$1.class"), Some(Vec::new()));
+    }
+
+    #[test]
+    fn regular_classes_keep_their_naming() {
+        assert_eq!(names_of("jvmlang/Widget.class"), None);
+        assert_eq!(names_of("Widget.class"), None);
+    }
+
+    #[test]
+    fn scala_module_class_loses_its_dollar() {
+        assert_eq!(
+            names_of("jvmlang/Registry$.class"),
+            Some(vec!["Registry".to_string()])
+        );
+    }
+
+    #[test]
+    fn getters_become_properties() {
+        assert_eq!(
+            property_for_getter("getMaxSize").as_deref(),
+            Some("maxSize")
+        );
+        assert_eq!(property_for_getter("getter"), None);
+        assert_eq!(property_for_getter("get"), None);
+        assert_eq!(property_for_getter("size"), None);
+    }
+}
@@ -19,9 +19,22 @@ use serde_derive::Serialize;
 use zip::read::ZipArchive;
 use zip::result::ZipResult;
 
+pub mod channel;
+pub mod classfile;
+pub mod classpath;
 pub mod cli;
+pub mod config;
+pub mod gradle;
 pub mod indexes;
 pub mod jdk;
+pub mod jobs;
+pub mod jpms;
+pub mod jvmlang;
+pub mod manifest;
+pub mod maven;
+pub mod multimodule;
+pub mod pom;
+pub mod pool;
 pub mod project;
 pub mod proto;
 pub mod serve;
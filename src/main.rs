@@ -6,7 +6,7 @@ use std::env;
 use std::fs;
 use std::io::{stdin, stdout, Read};
 use std::os::unix::net::{UnixListener, UnixStream};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::thread;
@@ -25,12 +25,18 @@ use zip::read::ZipArchive;
 use zip::result::ZipResult;
 
 use cpid::cli;
+use cpid::config::ConfiguredProject;
+use cpid::gradle;
 use cpid::indexes::{
-    enumerate_indexes, reindex_classpath, reindex_jar_dir, reindex_jimage, reindex_project_path,
-    Index,
+    enumerate_indexes, reindex_classpath, reindex_configured_project, reindex_ct_sym,
+    reindex_gradle_cache, reindex_jar_dir, reindex_jdk_install, reindex_jdk_src, reindex_jimage,
+    reindex_jmod, reindex_m2, reindex_pom, reindex_project_path, Index,
 };
+use cpid::jdk;
 use cpid::jdk::is_jimage_file;
+use cpid::maven;
 use cpid::project::crawl_project;
+use cpid::proto;
 
 // types       SomeClassName: [my.pacakge.name, my.package.name.SomeClassName.class, my.package.name.jar]
 // type2pkg    SomeClassName: my.package.name
@@ -80,9 +86,20 @@ fn main() -> Result<()> {
         cli::Commands::ClsQuery {
             index_name,
             class_name,
+            include_unexported,
+            file_path,
+            scope,
         } => {
-            let results = Index::new(&db, &index_name).query_class_index(&class_name)?;
-            println!("{}", serde_json::to_string(&results)?);
+            let index = Index::new(&db, &index_name);
+            let filter = index.query_filter(
+                include_unexported,
+                file_path.as_deref().map(Path::new),
+                scope,
+            )?;
+            let results = index.query_class_index_with(&class_name, &filter)?;
+            let modules = proto::result_modules(&index, &results)?;
+            let response = proto::ClassQueryResponseArgs::new(results).with_modules(modules);
+            println!("{}", serde_json::to_string(&response)?);
             Ok(())
         }
         cli::Commands::PkgEnum {
@@ -93,12 +110,48 @@ fn main() -> Result<()> {
             println!("{}", serde_json::to_string(&results)?);
             Ok(())
         }
+        cli::Commands::SrcQuery {
+            index_name,
+            class_name,
+        } => {
+            let results = Index::new(&db, &index_name).query_source_index(&class_name)?;
+            println!("{}", serde_json::to_string(&results)?);
+            Ok(())
+        }
+        cli::Commands::CoordQuery {
+            index_name,
+            class_name,
+        } => {
+            let results = Index::new(&db, &index_name).query_coordinate_index(&class_name)?;
+            println!("{}", serde_json::to_string(&results)?);
+            Ok(())
+        }
         cli::Commands::DropIndex { index_name } => Index::new(&db, &index_name).drop_trees(),
+        cli::Commands::Settings {
+            index_name,
+            target_release,
+            clear_target_release,
+            jvm_lang_names,
+        } => {
+            let index = Index::new(&db, &index_name);
+            let mut settings = index.settings()?;
+            if target_release.is_some() || clear_target_release {
+                settings.target_release = target_release;
+                index.store_settings(&settings)?;
+            }
+            if let Some(jvm_lang_names) = jvm_lang_names {
+                settings.jvm_lang_names = jvm_lang_names;
+                index.store_settings(&settings)?;
+            }
+            println!("{}", serde_json::to_string(&settings)?);
+            Ok(())
+        }
         cli::Commands::Reindex { reindex_command } => match reindex_command {
             cli::ReindexCommands::Classpath {
                 index_name,
                 classpath_expr,
-            } => reindex_classpath(&Index::new(&db, &index_name), &classpath_expr),
+                scope,
+            } => reindex_classpath(&Index::new(&db, &index_name), &classpath_expr, scope),
             cli::ReindexCommands::JarDir {
                 index_name,
                 jar_dir,
@@ -121,6 +174,130 @@ fn main() -> Result<()> {
                     Err(anyhow!("{image_file} is not a jimage file."))
                 }
             }
+            cli::ReindexCommands::Jdk { major_version, all } => {
+                let mut found = jdk::discover_jdks();
+                let selected = if all {
+                    // Earlier discoveries win when two installs share a major version.
+                    let mut majors: Vec<u32> = Vec::new();
+                    found.retain(|j| {
+                        let is_new = !majors.contains(&j.major);
+                        majors.push(j.major);
+                        is_new
+                    });
+                    found
+                } else if let Some(major) = major_version {
+                    found
+                        .into_iter()
+                        .filter(|j| j.major == major)
+                        .take(1)
+                        .collect()
+                } else {
+                    let java_home =
+                        jdk::java_home_from_env().and_then(|h| jdk::read_jdk_install(&h));
+                    java_home
+                        .or_else(|| found.into_iter().max_by_key(|j| j.major))
+                        .into_iter()
+                        .collect()
+                };
+
+                if selected.is_empty() {
+                    Err(Error::msg("No matching JDK found."))
+                } else {
+                    selected.iter().try_for_each(|j| {
+                        println!(
+                            "Indexing {} ({}) as {}",
+                            j.home.display(),
+                            j.version,
+                            j.index_name()
+                        );
+                        reindex_jdk_install(&Index::new(&db, &j.index_name()), j)
+                    })
+                }
+            }
+            cli::ReindexCommands::Jmod {
+                index_name,
+                jmod_path,
+            } => {
+                let path = Path::new(&jmod_path);
+                if path.is_dir() || jdk::is_jmod_file(path) {
+                    reindex_jmod(&Index::new(&db, &index_name), path)
+                } else {
+                    Err(anyhow!(
+                        "{jmod_path} is neither a jmod file nor a directory."
+                    ))
+                }
+            }
+            cli::ReindexCommands::CtSym {
+                index_name,
+                release,
+                java_home,
+            } => {
+                let ct_sym_path = java_home
+                    .map(PathBuf::from)
+                    .or_else(jdk::java_home_from_env)
+                    .map(|home| home.join("lib").join("ct.sym"))
+                    .ok_or_else(|| Error::msg("Pass --java-home or set JAVA_HOME."))?;
+                if ct_sym_path.is_file() {
+                    reindex_ct_sym(&Index::new(&db, &index_name), &ct_sym_path, release)
+                } else {
+                    Err(anyhow!("{} is not a file.", ct_sym_path.display()))
+                }
+            }
+            cli::ReindexCommands::JdkSrc {
+                index_name,
+                src_zip,
+            } => {
+                let src_zip_path = Path::new(&src_zip);
+                if src_zip_path.is_file() {
+                    reindex_jdk_src(&Index::new(&db, &index_name), src_zip_path)
+                } else {
+                    Err(anyhow!("{src_zip} is not a file."))
+                }
+            }
+            cli::ReindexCommands::M2 {
+                index_name,
+                repo_dir,
+            } => {
+                let repo_path = repo_dir
+                    .map(PathBuf::from)
+                    .or_else(maven::default_local_repository)
+                    .ok_or_else(|| Error::msg("Cannot locate the local Maven repository."))?;
+                if repo_path.is_dir() {
+                    reindex_m2(&Index::new(&db, &index_name), &repo_path)
+                } else {
+                    Err(anyhow!("{} is not a directory.", repo_path.display()))
+                }
+            }
+            cli::ReindexCommands::GradleCache {
+                index_name,
+                cache_dir,
+            } => {
+                let cache_path = cache_dir
+                    .map(PathBuf::from)
+                    .or_else(gradle::default_cache_dir)
+                    .ok_or_else(|| Error::msg("Cannot locate the Gradle module cache."))?;
+                if cache_path.is_dir() {
+                    reindex_gradle_cache(&Index::new(&db, &index_name), &cache_path)
+                } else {
+                    Err(anyhow!("{} is not a directory.", cache_path.display()))
+                }
+            }
+            cli::ReindexCommands::Pom {
+                index_name,
+                pom_file,
+                repo_dir,
+            } => {
+                let pom_path = Path::new(&pom_file);
+                let repo_path = repo_dir
+                    .map(PathBuf::from)
+                    .or_else(maven::default_local_repository)
+                    .ok_or_else(|| Error::msg("Cannot locate the local Maven repository."))?;
+                if pom_path.is_file() {
+                    reindex_pom(&Index::new(&db, &index_name), pom_path, &repo_path)
+                } else {
+                    Err(anyhow!("{pom_file} is not a file."))
+                }
+            }
             cli::ReindexCommands::Project {
                 index_name,
                 src_dir,
@@ -132,6 +309,10 @@ fn main() -> Result<()> {
                     Err(Error::msg("Project path must be a directory."))
                 }
             }
+            cli::ReindexCommands::Configured { path } => {
+                let project = ConfiguredProject::discover(Path::new(&path))?;
+                reindex_configured_project(&db, &project, None)
+            }
         },
         cli::Commands::Indexes => {
             let index_name_pat = Regex::new(r"(.+)-class_pkgs").unwrap();
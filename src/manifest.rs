@@ -0,0 +1,70 @@
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+
+use std::collections::HashMap;
+use std::io::{Read, Seek};
+
+use zip::read::ZipArchive;
+
+pub const MANIFEST_ENTRY: &str = "META-INF/MANIFEST.MF";
+
+/// The main section of a jar manifest. Per-entry sections are not needed by anything here and
+/// are ignored.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Manifest {
+    attributes: HashMap<String, String>,
+}
+
+impl Manifest {
+    /// Parses manifest text, joining continuation lines (those starting with a single space) onto
+    /// the line before them. Attribute names are case-insensitive.
+    pub fn parse(text: &str) -> Self {
+        let mut logical_lines: Vec<String> = Vec::new();
+        for line in text.lines() {
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            if line.is_empty() {
+                // A blank line ends the main section.
+                break;
+            }
+            match (line.strip_prefix(' '), logical_lines.last_mut()) {
+                (Some(continuation), Some(prev)) => prev.push_str(continuation),
+                _ => logical_lines.push(line.to_string()),
+            }
+        }
+
+        let attributes = logical_lines
+            .iter()
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+            .collect();
+        Manifest { attributes }
+    }
+
+    pub fn from_zip<R: Read + Seek>(archive: &mut ZipArchive<R>) -> Option<Self> {
+        let mut entry = archive.by_name(MANIFEST_ENTRY).ok()?;
+        let mut text = String::new();
+        entry.read_to_string(&mut text).ok()?;
+        Some(Manifest::parse(&text))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .get(&name.to_lowercase())
+            .map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_joins_continuation_lines_of_the_main_section() {
+        let manifest = Manifest::parse(
+            "Manifest-Version: 1.0\r\nClass-Path: lib/a.jar lib/b\r\n .jar\r\n\r\nName: x\r\nClass-Path: ignored\r\n",
+        );
+        assert_eq!(manifest.get("class-path"), Some("lib/a.jar lib/b.jar"));
+        assert_eq!(manifest.get("MANIFEST-VERSION"), Some("1.0"));
+        assert_eq!(manifest.get("Name"), None);
+    }
+}
@@ -0,0 +1,236 @@
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Coordinate {
+    pub group_id: String,
+    pub artifact_id: String,
+    pub version: String,
+}
+
+impl Coordinate {
+    pub fn new(group_id: &str, artifact_id: &str, version: &str) -> Self {
+        Coordinate {
+            group_id: group_id.to_string(),
+            artifact_id: artifact_id.to_string(),
+            version: version.to_string(),
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Self> {
+        match s.split(':').collect::<Vec<&str>>()[..] {
+            [g, a, v] => Ok(Coordinate::new(g, a, v)),
+            _ => Err(anyhow!(
+                "Not a groupId:artifactId:version coordinate: {}",
+                s
+            )),
+        }
+    }
+
+    /// The `groupId:artifactId` part, which identifies the artifact across versions.
+    pub fn key(&self) -> String {
+        format!("{}:{}", self.group_id, self.artifact_id)
+    }
+}
+
+impl fmt::Display for Coordinate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.group_id, self.artifact_id, self.version)
+    }
+}
+
+pub fn default_local_repository() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| Path::new(&home).join(".m2").join("repository"))
+}
+
+/// Derives the coordinate of a jar from its location in a local repository, which is laid out as
+/// `<group/as/dirs>/<artifactId>/<version>/<artifactId>-<version>.jar`. Jars carrying a
+/// classifier (`-sources`, `-javadoc`, `-tests`, ...) are not the artifact itself and are ignored.
+pub fn coordinate_for_jar(repo_root: &Path, jar_path: &Path) -> Option<Coordinate> {
+    let rel = jar_path.strip_prefix(repo_root).ok()?;
+    let mut parts = rel
+        .iter()
+        .map(|p| p.to_str())
+        .collect::<Option<Vec<&str>>>()?;
+    if parts.len() < 4 {
+        return None;
+    }
+
+    let file_name = parts.pop()?;
+    let version = parts.pop()?;
+    let artifact_id = parts.pop()?;
+    if file_name != format!("{}-{}.jar", artifact_id, version) {
+        return None;
+    }
+    Some(Coordinate::new(&parts.join("."), artifact_id, version))
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum VersionItem {
+    Number(u64),
+    Qualifier(String),
+}
+
+fn qualifier_rank(q: &str) -> Option<i32> {
+    match q {
+        "alpha" | "a" => Some(0),
+        "beta" | "b" => Some(1),
+        "milestone" | "m" => Some(2),
+        "rc" | "cr" => Some(3),
+        "snapshot" => Some(4),
+        "" | "ga" | "final" | "release" => Some(5),
+        "sp" => Some(6),
+        _ => None,
+    }
+}
+
+fn version_items(version: &str) -> Vec<VersionItem> {
+    let mut items = Vec::new();
+    let mut token = String::new();
+    let flush = |token: &mut String, items: &mut Vec<VersionItem>| {
+        if !token.is_empty() {
+            match token.parse::<u64>() {
+                Ok(n) => items.push(VersionItem::Number(n)),
+                Err(_) => items.push(VersionItem::Qualifier(token.to_lowercase())),
+            }
+            token.clear();
+        }
+    };
+
+    for c in version.chars() {
+        if c == '.' || c == '-' || c == '_' {
+            flush(&mut token, &mut items);
+        } else {
+            // A switch between digits and letters ("1.0rc1") also separates items.
+            let is_boundary = token
+                .chars()
+                .last()
+                .map(|prev| prev.is_ascii_digit() != c.is_ascii_digit())
+                .unwrap_or(false);
+            if is_boundary {
+                flush(&mut token, &mut items);
+            }
+            token.push(c);
+        }
+    }
+    flush(&mut token, &mut items);
+
+    // Trailing zeroes and release qualifiers do not change the version: 1.0 == 1 == 1.0.0-ga.
+    while let Some(last) = items.last() {
+        match last {
+            VersionItem::Number(0) => items.pop(),
+            VersionItem::Qualifier(q) if qualifier_rank(q) == Some(5) => items.pop(),
+            _ => break,
+        };
+    }
+    items
+}
+
+fn compare_items(a: Option<&VersionItem>, b: Option<&VersionItem>) -> Ordering {
+    use VersionItem::*;
+    match (a, b) {
+        (None, None) => Ordering::Equal,
+        (Some(Number(x)), Some(Number(y))) => x.cmp(y),
+        // Missing numeric components are zero: 2.0-rc1 lines up with 2.0.
+        (Some(Number(x)), None) => x.cmp(&0),
+        (None, Some(Number(y))) => 0.cmp(y),
+        // A number beats any qualifier: 1.1 > 1-rc1 and 1.1 > 1.sp.
+        (Some(Number(_)), _) => Ordering::Greater,
+        (_, Some(Number(_))) => Ordering::Less,
+        (Some(Qualifier(x)), Some(Qualifier(y))) => match (qualifier_rank(x), qualifier_rank(y)) {
+            (Some(rx), Some(ry)) => rx.cmp(&ry),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => x.cmp(y),
+        },
+        // Running out of items means the other version carries a qualifier.
+        (Some(Qualifier(x)), None) => qualifier_rank(x).unwrap_or(6).cmp(&5),
+        (None, Some(Qualifier(y))) => 5.cmp(&qualifier_rank(y).unwrap_or(6)),
+    }
+}
+
+/// Orders versions the way Maven does for the common cases: numeric components compare
+/// numerically and pre-release qualifiers sort before the release they precede.
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let items_a = version_items(a);
+    let items_b = version_items(b);
+    let len = items_a.len().max(items_b.len());
+    for idx in 0..len {
+        match compare_items(items_a.get(idx), items_b.get(idx)) {
+            Ordering::Equal => continue,
+            ord => return ord,
+        }
+    }
+    Ordering::Equal
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numeric_components_compare_numerically() {
+        assert_eq!(compare_versions("1.0", "1.0.1"), Ordering::Less);
+        assert_eq!(compare_versions("1.10", "1.9"), Ordering::Greater);
+        assert_eq!(compare_versions("1.0", "1.0.0"), Ordering::Equal);
+        assert_eq!(compare_versions("1", "1.0.0-ga"), Ordering::Equal);
+    }
+
+    #[test]
+    fn qualifiers_sort_around_their_release() {
+        let ascending = [
+            "1.0-alpha",
+            "1.0-beta",
+            "1.0-rc",
+            "1.0-SNAPSHOT",
+            "1.0",
+            "1.0-sp",
+            "1.0.1",
+        ];
+        for pair in ascending.windows(2) {
+            assert_eq!(
+                compare_versions(pair[0], pair[1]),
+                Ordering::Less,
+                "{} < {}",
+                pair[0],
+                pair[1]
+            );
+            assert_eq!(compare_versions(pair[1], pair[0]), Ordering::Greater);
+        }
+        assert_eq!(compare_versions("1.0rc1", "1.0-rc-1"), Ordering::Equal);
+    }
+
+    #[test]
+    fn coordinate_for_jar_reads_the_repository_layout() {
+        let repo = Path::new("/home/user/.m2/repository");
+        assert_eq!(
+            coordinate_for_jar(
+                repo,
+                &repo.join("org/apache/commons/commons-math3/3.2/commons-math3-3.2.jar")
+            ),
+            Some(Coordinate::new(
+                "org.apache.commons",
+                "commons-math3",
+                "3.2"
+            ))
+        );
+        assert_eq!(
+            coordinate_for_jar(repo, &repo.join("junit/junit/4.11/junit-4.11-sources.jar")),
+            None
+        );
+        assert_eq!(
+            coordinate_for_jar(repo, Path::new("/opt/lib/junit/4.11/junit-4.11.jar")),
+            None
+        );
+        assert_eq!(
+            coordinate_for_jar(repo, &repo.join("4.11/junit-4.11.jar")),
+            None
+        );
+    }
+}
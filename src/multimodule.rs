@@ -0,0 +1,295 @@
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use regex::Regex;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::pom::Pom;
+
+const POM_FILE: &str = "pom.xml";
+const GRADLE_SETTINGS_FILES: [&str; 2] = ["settings.gradle", "settings.gradle.kts"];
+const GRADLE_BUILD_FILES: [&str; 2] = ["build.gradle", "build.gradle.kts"];
+
+/// One module of a multi-module build. Modules are identified by their directory, which keeps
+/// them apart even when several projects share an index.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct BuildModule {
+    pub dir: PathBuf,
+    /// The artifactId of a Maven module or the project path of a Gradle one.
+    pub name: String,
+    /// Directories of the sibling modules this one depends on.
+    pub dependencies: Vec<PathBuf>,
+}
+
+fn maven_group_id(pom: &Pom) -> Option<String> {
+    pom.group_id.clone().or_else(|| {
+        pom.parent
+            .as_ref()
+            .map(|(parent, _)| parent.group_id.clone())
+    })
+}
+
+/// Resolves `..` and symlinks in a path so that it compares equal to the module directories.
+/// Of a path that does not exist, the longest existing prefix is resolved and the rest kept.
+pub fn canonical_path(path: &Path) -> PathBuf {
+    for prefix in path.ancestors() {
+        if let Ok(canonical) = fs::canonicalize(prefix) {
+            return match path.strip_prefix(prefix) {
+                Ok(rest) if !rest.as_os_str().is_empty() => canonical.join(rest),
+                _ => canonical,
+            };
+        }
+    }
+    path.to_path_buf()
+}
+
+/// Collects the poms of every module below `dir`, following `<modules>` recursively. Module
+/// paths such as `../sibling` are canonicalized, and a module listed twice is read once.
+fn collect_maven_modules(
+    dir: &Path,
+    visited: &mut HashSet<PathBuf>,
+    accum: &mut Vec<(PathBuf, Pom)>,
+) -> Result<()> {
+    let pom = Pom::from_file(&dir.join(POM_FILE))?;
+    for module in pom.modules.iter() {
+        let module_dir = canonical_path(&dir.join(module));
+        if module_dir.join(POM_FILE).is_file() && visited.insert(module_dir.clone()) {
+            collect_maven_modules(&module_dir, visited, accum)?;
+        }
+    }
+    accum.push((dir.to_path_buf(), pom));
+    Ok(())
+}
+
+fn detect_maven_modules(project_root: &Path) -> Result<Vec<BuildModule>> {
+    let project_root = canonical_path(project_root);
+    let mut poms: Vec<(PathBuf, Pom)> = Vec::new();
+    let mut visited: HashSet<PathBuf> = HashSet::from([project_root.clone()]);
+    collect_maven_modules(&project_root, &mut visited, &mut poms)?;
+    if poms.len() < 2 {
+        return Ok(Vec::new());
+    }
+
+    let dirs_by_key: HashMap<String, PathBuf> = poms
+        .iter()
+        .filter_map(|(dir, pom)| {
+            let group_id = maven_group_id(pom)?;
+            Some((format!("{}:{}", group_id, pom.artifact_id), dir.clone()))
+        })
+        .collect();
+
+    let modules = poms
+        .iter()
+        .map(|(dir, pom)| {
+            let own_group_id = maven_group_id(pom).unwrap_or_default();
+            let dependencies = pom
+                .dependencies
+                .iter()
+                .filter_map(|dep| {
+                    // Sibling dependencies are usually declared with the reactor's own groupId.
+                    let group_id = match dep.group_id.as_str() {
+                        "${project.groupId}" | "${pom.groupId}" => own_group_id.as_str(),
+                        group_id => group_id,
+                    };
+                    dirs_by_key
+                        .get(&format!("{}:{}", group_id, dep.artifact_id))
+                        .cloned()
+                })
+                .collect();
+            BuildModule {
+                dir: dir.clone(),
+                name: pom.artifact_id.clone(),
+                dependencies,
+            }
+        })
+        .collect();
+    Ok(modules)
+}
+
+/// Reads the project paths a Gradle settings script includes, as in `include 'app', ':lib:core'`
+/// or `include(":app")`. Paths are returned without their leading ':'.
+fn gradle_includes(settings: &str) -> Vec<String> {
+    let include_pat = Regex::new(r"^\s*include\b\s*\(?(.*)$").unwrap();
+    let path_pat = Regex::new(r#"["']:?([^"']+)["']"#).unwrap();
+    settings
+        .lines()
+        .filter_map(|line| include_pat.captures(line))
+        .flat_map(|caps| {
+            path_pat
+                .captures_iter(caps.get(1).unwrap().as_str())
+                .map(|path_caps| path_caps.get(1).unwrap().as_str().to_string())
+                .collect::<Vec<String>>()
+        })
+        .collect()
+}
+
+/// Reads the `project(':path')` dependencies of a Gradle build script.
+fn gradle_project_dependencies(build_script: &str) -> Vec<String> {
+    let project_pat =
+        Regex::new(r#"project\s*\(\s*(?:path\s*[:=]\s*)?["']:?([^"']+)["']"#).unwrap();
+    project_pat
+        .captures_iter(build_script)
+        .map(|caps| caps.get(1).unwrap().as_str().to_string())
+        .collect()
+}
+
+fn detect_gradle_modules(project_root: &Path) -> Result<Vec<BuildModule>> {
+    let project_root = canonical_path(project_root);
+    let settings = match GRADLE_SETTINGS_FILES
+        .iter()
+        .map(|name| project_root.join(name))
+        .find(|path| path.is_file())
+    {
+        Some(path) => fs::read_to_string(path)?,
+        None => return Ok(Vec::new()),
+    };
+
+    let project_dir = |project_path: &str| -> PathBuf {
+        canonical_path(
+            &project_path
+                .split(':')
+                .fold(project_root.to_path_buf(), |dir, part| dir.join(part)),
+        )
+    };
+
+    let modules = gradle_includes(&settings)
+        .into_iter()
+        .map(|project_path| {
+            let dir = project_dir(&project_path);
+            let build_script = GRADLE_BUILD_FILES
+                .iter()
+                .find_map(|name| fs::read_to_string(dir.join(name)).ok())
+                .unwrap_or_default();
+            let dependencies = gradle_project_dependencies(&build_script)
+                .iter()
+                .map(|dep_path| project_dir(dep_path))
+                .collect();
+            BuildModule {
+                dir,
+                name: project_path,
+                dependencies,
+            }
+        })
+        .collect();
+    Ok(modules)
+}
+
+/// Finds the modules of a multi-module Maven or Gradle build rooted at `project_root`. A
+/// single-module build has none.
+pub fn detect_modules(project_root: &Path) -> Result<Vec<BuildModule>> {
+    if project_root.join(POM_FILE).is_file() {
+        detect_maven_modules(project_root)
+    } else {
+        detect_gradle_modules(project_root)
+    }
+}
+
+/// Picks the innermost module containing a path.
+pub fn module_for_path<'a>(modules: &'a [BuildModule], path: &Path) -> Option<&'a BuildModule> {
+    modules
+        .iter()
+        .filter(|module| path.starts_with(&module.dir))
+        .max_by_key(|module| module.dir.components().count())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_file(path: &Path, contents: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    fn pom_xml(artifact_id: &str, body: &str) -> String {
+        format!(
+            "<project><artifactId>{}</artifactId>{}</project>",
+            artifact_id, body
+        )
+    }
+
+    fn sibling_dependency(group_id: &str, artifact_id: &str) -> String {
+        format!(
+            "<dependencies><dependency><groupId>{}</groupId><artifactId>{}</artifactId></dependency></dependencies>",
+            group_id, artifact_id
+        )
+    }
+
+    fn module<'a>(modules: &'a [BuildModule], name: &str) -> &'a BuildModule {
+        modules.iter().find(|m| m.name == name).unwrap()
+    }
+
+    #[test]
+    fn gradle_includes_reads_every_notation() {
+        let settings = "rootProject.name = 'app'\n\
+                        include 'api', ':lib:core'\n\
+                        include(\":web\")\n\
+                        // include 'commented'\n\
+                        includeBuild 'other'\n";
+        assert_eq!(gradle_includes(settings), vec!["api", "lib:core", "web"]);
+    }
+
+    #[test]
+    fn gradle_project_dependencies_reads_every_notation() {
+        let build_script = "dependencies {\n\
+                            implementation project(':api')\n\
+                            testImplementation(project(\":lib:core\"))\n\
+                            api project(path: ':web')\n\
+                            implementation 'org.example:lib:1.0'\n\
+                            }\n";
+        assert_eq!(
+            gradle_project_dependencies(build_script),
+            vec!["api", "lib:core", "web"]
+        );
+    }
+
+    #[test]
+    fn maven_siblings_are_found_through_the_reactor_group() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = fs::canonicalize(dir.path()).unwrap().join("parent");
+        write_file(
+            &root.join(POM_FILE),
+            &pom_xml(
+                "parent",
+                "<groupId>org.example</groupId>\
+                 <modules><module>app</module><module>../shared</module></modules>",
+            ),
+        );
+        let parent = "<parent><groupId>org.example</groupId><artifactId>parent</artifactId>\
+                      <version>1</version></parent>";
+        write_file(
+            &root.join("app").join(POM_FILE),
+            &pom_xml(
+                "app",
+                &format!(
+                    "{}{}",
+                    parent,
+                    sibling_dependency("${project.groupId}", "shared")
+                ),
+            ),
+        );
+        write_file(
+            &root.join("../shared").join(POM_FILE),
+            &pom_xml("shared", parent),
+        );
+
+        let modules = detect_modules(&root).unwrap();
+        assert_eq!(modules.len(), 3);
+        let shared_dir = root.parent().unwrap().join("shared");
+        assert_eq!(module(&modules, "shared").dir, shared_dir);
+        assert_eq!(module(&modules, "app").dependencies, vec![shared_dir]);
+        assert!(module(&modules, "parent").dependencies.is_empty());
+    }
+
+    #[test]
+    fn single_module_builds_have_no_modules() {
+        let dir = tempfile::tempdir().unwrap();
+        write_file(&dir.path().join(POM_FILE), &pom_xml("only", ""));
+        assert!(detect_modules(dir.path()).unwrap().is_empty());
+    }
+}
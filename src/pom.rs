@@ -0,0 +1,823 @@
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use roxmltree::Node;
+
+use crate::maven;
+use crate::maven::Coordinate;
+
+// Interpolated values may refer to other properties. Anything nested deeper than this is almost
+// certainly a cycle.
+const MAX_INTERPOLATION_DEPTH: usize = 16;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Dependency {
+    pub group_id: String,
+    pub artifact_id: String,
+    pub version: Option<String>,
+    pub scope: Option<String>,
+    pub dep_type: Option<String>,
+    pub classifier: Option<String>,
+    pub optional: bool,
+    pub exclusions: Vec<(String, String)>,
+}
+
+impl Dependency {
+    /// Identifies a dependency for management and conflict resolution purposes. The type and
+    /// classifier are part of the key because `foo:bar` and `foo:bar:tests` are separate files.
+    pub fn management_key(&self) -> String {
+        format!(
+            "{}:{}:{}:{}",
+            self.group_id,
+            self.artifact_id,
+            self.dep_type.as_deref().unwrap_or("jar"),
+            self.classifier.as_deref().unwrap_or("")
+        )
+    }
+
+    fn excludes(&self, group_id: &str, artifact_id: &str) -> bool {
+        self.exclusions
+            .iter()
+            .any(|(g, a)| (g == "*" || g == group_id) && (a == "*" || a == artifact_id))
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Pom {
+    pub group_id: Option<String>,
+    pub artifact_id: String,
+    pub version: Option<String>,
+    pub packaging: Option<String>,
+    pub parent: Option<(Coordinate, Option<String>)>,
+    pub properties: HashMap<String, String>,
+    pub dependency_management: Vec<Dependency>,
+    pub dependencies: Vec<Dependency>,
+    pub modules: Vec<String>,
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children()
+        .find(|n| n.is_element() && n.tag_name().name() == name)
+}
+
+fn children<'a, 'input>(
+    node: Node<'a, 'input>,
+    name: &'a str,
+) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+    node.children()
+        .filter(move |n| n.is_element() && n.tag_name().name() == name)
+}
+
+fn child_text(node: Node, name: &str) -> Option<String> {
+    child(node, name)
+        .and_then(|n| n.text())
+        .map(|t| t.trim().to_string())
+}
+
+fn parse_dependency(node: Node) -> Dependency {
+    let exclusions = child(node, "exclusions")
+        .map(|excl| {
+            children(excl, "exclusion")
+                .map(|e| {
+                    (
+                        child_text(e, "groupId").unwrap_or_else(|| "*".to_string()),
+                        child_text(e, "artifactId").unwrap_or_else(|| "*".to_string()),
+                    )
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Dependency {
+        group_id: child_text(node, "groupId").unwrap_or_default(),
+        artifact_id: child_text(node, "artifactId").unwrap_or_default(),
+        version: child_text(node, "version"),
+        scope: child_text(node, "scope"),
+        dep_type: child_text(node, "type"),
+        classifier: child_text(node, "classifier"),
+        optional: child_text(node, "optional").as_deref() == Some("true"),
+        exclusions,
+    }
+}
+
+fn parse_dependencies(node: Option<Node>) -> Vec<Dependency> {
+    node.map(|deps| children(deps, "dependency").map(parse_dependency).collect())
+        .unwrap_or_default()
+}
+
+impl Pom {
+    pub fn parse(text: &str) -> Result<Self> {
+        let doc = roxmltree::Document::parse(text)?;
+        let project = doc.root_element();
+        if project.tag_name().name() != "project" {
+            return Err(anyhow!(
+                "Not a Maven POM: the root element is not <project>."
+            ));
+        }
+
+        let parent = child(project, "parent").and_then(|p| {
+            Some((
+                Coordinate::new(
+                    &child_text(p, "groupId")?,
+                    &child_text(p, "artifactId")?,
+                    &child_text(p, "version")?,
+                ),
+                // An empty <relativePath/> is kept: it says not to look for the parent locally.
+                child(p, "relativePath").map(|n| n.text().unwrap_or("").trim().to_string()),
+            ))
+        });
+
+        let properties = child(project, "properties")
+            .map(|props| {
+                props
+                    .children()
+                    .filter(|n| n.is_element())
+                    .map(|n| {
+                        (
+                            n.tag_name().name().to_string(),
+                            n.text().unwrap_or("").trim().to_string(),
+                        )
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let modules = child(project, "modules")
+            .map(|mods| {
+                children(mods, "module")
+                    .filter_map(|m| m.text())
+                    .map(|t| t.trim().to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(Pom {
+            group_id: child_text(project, "groupId"),
+            artifact_id: child_text(project, "artifactId").unwrap_or_default(),
+            version: child_text(project, "version"),
+            packaging: child_text(project, "packaging"),
+            parent,
+            properties,
+            dependency_management: parse_dependencies(
+                child(project, "dependencyManagement").and_then(|dm| child(dm, "dependencies")),
+            ),
+            dependencies: parse_dependencies(child(project, "dependencies")),
+            modules,
+        })
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Reading POM file {}", path.display()))?;
+        Pom::parse(&text).with_context(|| format!("Parsing POM file {}", path.display()))
+    }
+}
+
+/// A POM with its parents folded in, its properties interpolated and its BOM imports applied.
+#[derive(Clone, Debug)]
+pub struct EffectivePom {
+    pub coordinate: Coordinate,
+    pub dependency_management: HashMap<String, Dependency>,
+    pub dependencies: Vec<Dependency>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResolvedArtifact {
+    pub coordinate: Coordinate,
+    pub scope: String,
+    pub jar_path: PathBuf,
+}
+
+fn interpolate(value: &str, props: &HashMap<String, String>) -> String {
+    let mut result = value.to_string();
+    let mut search_from = 0;
+    let mut substitutions = 0;
+    while substitutions < MAX_INTERPOLATION_DEPTH {
+        let start = match result[search_from..].find("${") {
+            Some(start) => search_from + start,
+            None => break,
+        };
+        let end = match result[start..].find('}') {
+            Some(end) => start + end,
+            None => break,
+        };
+        match props.get(&result[start + 2..end]) {
+            Some(replacement) => {
+                result = format!("{}{}{}", &result[..start], replacement, &result[end + 1..]);
+                substitutions += 1;
+            }
+            // Leave unknown properties in place. They typically come from profiles or the
+            // environment and there is nothing better to substitute.
+            None => search_from = end + 1,
+        }
+    }
+    result
+}
+
+fn interpolate_dependency(dep: &Dependency, props: &HashMap<String, String>) -> Dependency {
+    let opt = |v: &Option<String>| v.as_ref().map(|s| interpolate(s, props));
+    Dependency {
+        group_id: interpolate(&dep.group_id, props),
+        artifact_id: interpolate(&dep.artifact_id, props),
+        version: opt(&dep.version),
+        scope: opt(&dep.scope),
+        dep_type: opt(&dep.dep_type),
+        classifier: opt(&dep.classifier),
+        optional: dep.optional,
+        exclusions: dep
+            .exclusions
+            .iter()
+            .map(|(g, a)| (interpolate(g, props), interpolate(a, props)))
+            .collect(),
+    }
+}
+
+fn is_version_range(version: &str) -> bool {
+    version.starts_with('[') || version.starts_with('(')
+}
+
+fn version_in_range(version: &str, range: &str) -> bool {
+    let inner = &range[1..range.len().saturating_sub(1)];
+    let lower_inclusive = range.starts_with('[');
+    let upper_inclusive = range.ends_with(']');
+    match inner.split_once(',') {
+        None => maven::compare_versions(version, inner.trim()).is_eq(),
+        Some((lower, upper)) => {
+            let (lower, upper) = (lower.trim(), upper.trim());
+            let lower_ok = lower.is_empty() || {
+                let ord = maven::compare_versions(version, lower);
+                ord.is_gt() || (lower_inclusive && ord.is_eq())
+            };
+            let upper_ok = upper.is_empty() || {
+                let ord = maven::compare_versions(version, upper);
+                ord.is_lt() || (upper_inclusive && ord.is_eq())
+            };
+            lower_ok && upper_ok
+        }
+    }
+}
+
+/// Combines the scope a dependency was reached through with the scope it is declared with, per
+/// the table in Maven's "Introduction to the Dependency Mechanism". `None` means the dependency
+/// is not transitive.
+fn transitive_scope(parent_scope: &str, declared_scope: &str) -> Option<String> {
+    match (parent_scope, declared_scope) {
+        (_, "provided") | (_, "test") | (_, "system") | (_, "import") => None,
+        ("compile", s) => Some(s.to_string()),
+        ("provided", _) => Some("provided".to_string()),
+        ("runtime", _) => Some("runtime".to_string()),
+        ("test", _) => Some("test".to_string()),
+        (p, _) => Some(p.to_string()),
+    }
+}
+
+/// Resolves POMs against a local repository without touching the network. Effective POMs are
+/// cached by coordinate since the same parents and BOMs are reached many times over.
+pub struct Resolver {
+    repo_root: PathBuf,
+    effective_cache: HashMap<Coordinate, EffectivePom>,
+    /// Coordinates whose effective POM is being built, to catch BOMs that import each other.
+    in_progress: HashSet<Coordinate>,
+}
+
+impl Resolver {
+    pub fn new(repo_root: &Path) -> Self {
+        Resolver {
+            repo_root: repo_root.to_path_buf(),
+            effective_cache: HashMap::new(),
+            in_progress: HashSet::new(),
+        }
+    }
+
+    fn artifact_dir(&self, group_id: &str, artifact_id: &str) -> PathBuf {
+        let mut dir = self.repo_root.clone();
+        dir.extend(group_id.split('.'));
+        dir.push(artifact_id);
+        dir
+    }
+
+    fn artifact_path(
+        &self,
+        coordinate: &Coordinate,
+        classifier: Option<&str>,
+        ext: &str,
+    ) -> PathBuf {
+        let file_name = match classifier {
+            Some(c) if !c.is_empty() => format!(
+                "{}-{}-{}.{}",
+                coordinate.artifact_id, coordinate.version, c, ext
+            ),
+            _ => format!("{}-{}.{}", coordinate.artifact_id, coordinate.version, ext),
+        };
+        self.artifact_dir(&coordinate.group_id, &coordinate.artifact_id)
+            .join(&coordinate.version)
+            .join(file_name)
+    }
+
+    /// Picks the newest locally available version satisfying a version range.
+    fn resolve_range(&self, group_id: &str, artifact_id: &str, range: &str) -> Option<String> {
+        fs::read_dir(self.artifact_dir(group_id, artifact_id))
+            .ok()?
+            .filter_map(|e| e.ok())
+            .filter_map(|e| e.file_name().to_str().map(String::from))
+            .filter(|v| version_in_range(v, range))
+            .max_by(|a, b| maven::compare_versions(a, b))
+    }
+
+    fn load_parent(
+        &self,
+        pom_path: Option<&Path>,
+        parent: &(Coordinate, Option<String>),
+    ) -> Result<(Pom, Option<PathBuf>)> {
+        let (coordinate, relative_path) = parent;
+        let relative_path = match relative_path.as_deref() {
+            Some("") => None,
+            Some(relative_path) => Some(relative_path),
+            None => Some("../pom.xml"),
+        };
+        if let Some((dir, relative_path)) = pom_path.and_then(|p| p.parent()).zip(relative_path) {
+            let mut candidate = dir.join(relative_path);
+            if candidate.is_dir() {
+                candidate.push("pom.xml");
+            }
+            if candidate.is_file() {
+                if let Ok(pom) = Pom::from_file(&candidate) {
+                    if pom.artifact_id == coordinate.artifact_id {
+                        return Ok((pom, Some(candidate)));
+                    }
+                }
+            }
+        }
+
+        let repo_pom = self.artifact_path(coordinate, None, "pom");
+        Ok((Pom::from_file(&repo_pom)?, Some(repo_pom)))
+    }
+
+    /// Builds the effective model of a POM: inheritance first, then interpolation over the merged
+    /// model, then BOM imports, matching the order Maven's model builder uses.
+    pub fn effective_pom(&mut self, pom: &Pom, pom_path: Option<&Path>) -> Result<EffectivePom> {
+        let mut lineage: Vec<Pom> = vec![pom.clone()];
+        let mut current_path = pom_path.map(Path::to_path_buf);
+        let mut seen: HashSet<Coordinate> = HashSet::new();
+        while let Some(parent) = lineage.last().and_then(|p| p.parent.clone()) {
+            if !seen.insert(parent.0.clone()) {
+                return Err(anyhow!("Cyclic POM inheritance at {}", parent.0));
+            }
+            let (parent_pom, parent_path) = self.load_parent(current_path.as_deref(), &parent)?;
+            lineage.push(parent_pom);
+            current_path = parent_path;
+        }
+
+        let group_id = pom
+            .group_id
+            .clone()
+            .or_else(|| pom.parent.as_ref().map(|p| p.0.group_id.clone()))
+            .unwrap_or_default();
+        let version = pom
+            .version
+            .clone()
+            .or_else(|| pom.parent.as_ref().map(|p| p.0.version.clone()))
+            .unwrap_or_default();
+
+        // Properties from the root ancestor down, so that children override their parents.
+        let mut props: HashMap<String, String> = HashMap::new();
+        for ancestor in lineage.iter().rev() {
+            props.extend(ancestor.properties.clone());
+        }
+        for prefix in ["project", "pom"] {
+            props.insert(format!("{}.groupId", prefix), group_id.clone());
+            props.insert(format!("{}.artifactId", prefix), pom.artifact_id.clone());
+            props.insert(format!("{}.version", prefix), version.clone());
+        }
+        props.insert("version".to_string(), version.clone());
+        if let Some((parent, _)) = &pom.parent {
+            props.insert(
+                "project.parent.groupId".to_string(),
+                parent.group_id.clone(),
+            );
+            props.insert(
+                "project.parent.artifactId".to_string(),
+                parent.artifact_id.clone(),
+            );
+            props.insert("project.parent.version".to_string(), parent.version.clone());
+        }
+
+        let coordinate = Coordinate::new(
+            &interpolate(&group_id, &props),
+            &interpolate(&pom.artifact_id, &props),
+            &interpolate(&version, &props),
+        );
+
+        // Nearer declarations win, both for managed and for direct dependencies.
+        let mut dependency_management: HashMap<String, Dependency> = HashMap::new();
+        let mut imports: Vec<Dependency> = Vec::new();
+        for ancestor in lineage.iter() {
+            for dep in ancestor.dependency_management.iter() {
+                let dep = interpolate_dependency(dep, &props);
+                if dep.scope.as_deref() == Some("import") {
+                    imports.push(dep);
+                } else {
+                    dependency_management
+                        .entry(dep.management_key())
+                        .or_insert(dep);
+                }
+            }
+        }
+
+        for bom in imports {
+            let bom_version = match bom.version.as_deref() {
+                Some(v) => v.to_string(),
+                None => continue,
+            };
+            let bom_coordinate = Coordinate::new(&bom.group_id, &bom.artifact_id, &bom_version);
+            match self.effective_pom_for(&bom_coordinate) {
+                Ok(bom_pom) => {
+                    for (key, dep) in bom_pom.dependency_management {
+                        dependency_management.entry(key).or_insert(dep);
+                    }
+                }
+                Err(e) => eprintln!("Skipping BOM import {}: {}", bom_coordinate, e),
+            }
+        }
+
+        let mut dependencies: Vec<Dependency> = Vec::new();
+        let mut dependency_keys: HashSet<String> = HashSet::new();
+        for ancestor in lineage.iter() {
+            for dep in ancestor.dependencies.iter() {
+                let mut dep = interpolate_dependency(dep, &props);
+                if !dependency_keys.insert(dep.management_key()) {
+                    continue;
+                }
+                if let Some(managed) = dependency_management.get(&dep.management_key()) {
+                    if dep.version.is_none() {
+                        dep.version = managed.version.clone();
+                    }
+                    if dep.scope.is_none() {
+                        dep.scope = managed.scope.clone();
+                    }
+                    if dep.exclusions.is_empty() {
+                        dep.exclusions = managed.exclusions.clone();
+                    }
+                }
+                dependencies.push(dep);
+            }
+        }
+
+        Ok(EffectivePom {
+            coordinate,
+            dependency_management,
+            dependencies,
+        })
+    }
+
+    pub fn effective_pom_for(&mut self, coordinate: &Coordinate) -> Result<EffectivePom> {
+        if let Some(cached) = self.effective_cache.get(coordinate) {
+            return Ok(cached.clone());
+        }
+        if !self.in_progress.insert(coordinate.clone()) {
+            return Err(anyhow!("Cyclic POM import at {}", coordinate));
+        }
+        let pom_path = self.artifact_path(coordinate, None, "pom");
+        let effective =
+            Pom::from_file(&pom_path).and_then(|pom| self.effective_pom(&pom, Some(&pom_path)));
+        self.in_progress.remove(coordinate);
+        let effective = effective?;
+        self.effective_cache
+            .insert(coordinate.clone(), effective.clone());
+        Ok(effective)
+    }
+
+    /// Walks the dependency graph of a project breadth first so that the nearest declaration of
+    /// an artifact wins, as it does in Maven. The project's own dependency management overrides
+    /// versions and scopes of transitive dependencies. Artifacts whose jar is missing from the
+    /// local repository are reported and left out.
+    pub fn resolve_project(&mut self, pom_path: &Path) -> Result<Vec<ResolvedArtifact>> {
+        let pom = Pom::from_file(pom_path)?;
+        let root = self.effective_pom(&pom, Some(pom_path))?;
+
+        let mut resolved: Vec<ResolvedArtifact> = Vec::new();
+        let mut seen: HashSet<String> = HashSet::new();
+        let mut queue: VecDeque<(Dependency, String, Vec<Dependency>)> = root
+            .dependencies
+            .iter()
+            .map(|dep| {
+                let scope = dep.scope.clone().unwrap_or_else(|| "compile".to_string());
+                (dep.clone(), scope, Vec::new())
+            })
+            .collect();
+
+        while let Some((dep, scope, path)) = queue.pop_front() {
+            let ga_key = format!("{}:{}", dep.group_id, dep.artifact_id);
+            if !seen.insert(dep.management_key()) {
+                continue;
+            }
+
+            let version = match dep.version.as_deref() {
+                Some(v) if is_version_range(v) => {
+                    match self.resolve_range(&dep.group_id, &dep.artifact_id, v) {
+                        Some(v) => v,
+                        None => {
+                            eprintln!("No local version of {} satisfies {}", ga_key, v);
+                            continue;
+                        }
+                    }
+                }
+                Some(v) => v.to_string(),
+                None => {
+                    eprintln!("No version declared or managed for {}", ga_key);
+                    continue;
+                }
+            };
+            let coordinate = Coordinate::new(&dep.group_id, &dep.artifact_id, &version);
+
+            let dep_type = dep.dep_type.as_deref().unwrap_or("jar");
+            if dep_type != "pom" {
+                let classifier = match (dep_type, dep.classifier.as_deref()) {
+                    ("test-jar", None) => Some("tests"),
+                    (_, c) => c,
+                };
+                let jar_path = self.artifact_path(&coordinate, classifier, "jar");
+                if jar_path.is_file() {
+                    resolved.push(ResolvedArtifact {
+                        coordinate: coordinate.clone(),
+                        scope: scope.clone(),
+                        jar_path,
+                    });
+                } else {
+                    eprintln!("Missing from the local repository: {}", jar_path.display());
+                }
+            }
+
+            let effective = match self.effective_pom_for(&coordinate) {
+                Ok(effective) => effective,
+                Err(e) => {
+                    eprintln!("Not following dependencies of {}: {}", coordinate, e);
+                    continue;
+                }
+            };
+
+            let mut child_path = path.clone();
+            child_path.push(dep.clone());
+            for child in effective.dependencies {
+                if child.optional
+                    || path
+                        .iter()
+                        .chain([&dep])
+                        .any(|d| d.excludes(&child.group_id, &child.artifact_id))
+                {
+                    continue;
+                }
+                let declared_scope = child.scope.clone().unwrap_or_else(|| "compile".to_string());
+                let child_scope = match transitive_scope(&scope, &declared_scope) {
+                    Some(s) => s,
+                    None => continue,
+                };
+
+                let mut child = child;
+                if let Some(managed) = root.dependency_management.get(&child.management_key()) {
+                    if managed.version.is_some() {
+                        child.version = managed.version.clone();
+                    }
+                }
+                let child_scope = root
+                    .dependency_management
+                    .get(&child.management_key())
+                    .and_then(|managed| managed.scope.clone())
+                    .unwrap_or(child_scope);
+                queue.push_back((child, child_scope, child_path.clone()));
+            }
+        }
+
+        Ok(resolved)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes a POM, and an empty jar next to it, into a local repository layout.
+    fn install(repo: &Path, coordinate: &str, body: &str) {
+        let coordinate = Coordinate::parse(coordinate).unwrap();
+        let resolver = Resolver::new(repo);
+        let pom_path = resolver.artifact_path(&coordinate, None, "pom");
+        fs::create_dir_all(pom_path.parent().unwrap()).unwrap();
+        fs::write(&pom_path, project_xml(&coordinate, body)).unwrap();
+        fs::write(resolver.artifact_path(&coordinate, None, "jar"), b"").unwrap();
+    }
+
+    fn project_xml(coordinate: &Coordinate, body: &str) -> String {
+        format!(
+            "<project><groupId>{}</groupId><artifactId>{}</artifactId><version>{}</version>{}</project>",
+            coordinate.group_id, coordinate.artifact_id, coordinate.version, body
+        )
+    }
+
+    fn dependency(coordinate: &str, extra: &str) -> String {
+        let coordinate = Coordinate::parse(coordinate).unwrap();
+        format!(
+            "<dependency><groupId>{}</groupId><artifactId>{}</artifactId><version>{}</version>{}</dependency>",
+            coordinate.group_id, coordinate.artifact_id, coordinate.version, extra
+        )
+    }
+
+    fn resolved_versions(artifacts: &[ResolvedArtifact]) -> Vec<(String, String)> {
+        artifacts
+            .iter()
+            .map(|a| (a.coordinate.to_string(), a.scope.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn interpolate_substitutes_nested_and_leaves_unknown_properties() {
+        let props: HashMap<String, String> = [
+            ("junit.version", "4.11"),
+            ("version.ref", "${junit.version}"),
+        ]
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        assert_eq!(interpolate("${junit.version}", &props), "4.11");
+        assert_eq!(interpolate("v${version.ref}-x", &props), "v4.11-x");
+        assert_eq!(
+            interpolate("${env.HOME}/${junit.version}", &props),
+            "${env.HOME}/4.11"
+        );
+        assert_eq!(interpolate("${unterminated", &props), "${unterminated");
+    }
+
+    #[test]
+    fn interpolate_stops_on_cycles() {
+        let props: HashMap<String, String> = [("a", "${b}"), ("b", "${a}")]
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        let value = interpolate("${a}", &props);
+        assert!(value == "${a}" || value == "${b}");
+    }
+
+    #[test]
+    fn version_in_range_honours_bounds() {
+        assert!(version_in_range("1.5", "[1.0,2.0)"));
+        assert!(version_in_range("1.0", "[1.0,2.0)"));
+        assert!(!version_in_range("2.0", "[1.0,2.0)"));
+        assert!(!version_in_range("1.0", "(1.0,2.0]"));
+        assert!(version_in_range("2.0", "(1.0,2.0]"));
+        assert!(version_in_range("9.1", "[1.0,)"));
+        assert!(version_in_range("0.1", "(,1.0]"));
+        assert!(version_in_range("1.2", "[1.2]"));
+        assert!(!version_in_range("1.2.1", "[1.2]"));
+    }
+
+    #[test]
+    fn transitive_scope_follows_the_maven_table() {
+        let s = |parent, declared| transitive_scope(parent, declared);
+        assert_eq!(s("compile", "compile").as_deref(), Some("compile"));
+        assert_eq!(s("compile", "runtime").as_deref(), Some("runtime"));
+        assert_eq!(s("runtime", "compile").as_deref(), Some("runtime"));
+        assert_eq!(s("test", "compile").as_deref(), Some("test"));
+        assert_eq!(s("test", "runtime").as_deref(), Some("test"));
+        assert_eq!(s("provided", "compile").as_deref(), Some("provided"));
+        assert_eq!(s("compile", "provided"), None);
+        assert_eq!(s("compile", "test"), None);
+        assert_eq!(s("runtime", "system"), None);
+    }
+
+    #[test]
+    fn nearest_declaration_wins() {
+        let repo = tempfile::tempdir().unwrap();
+        install(
+            repo.path(),
+            "g:a:1",
+            &format!("<dependencies>{}</dependencies>", dependency("g:c:1", "")),
+        );
+        install(
+            repo.path(),
+            "g:b:1",
+            &format!("<dependencies>{}</dependencies>", dependency("g:c:3", "")),
+        );
+        install(repo.path(), "g:c:1", "");
+        install(repo.path(), "g:c:2", "");
+        install(repo.path(), "g:c:3", "");
+
+        // c:2 is declared directly, so it beats c:1 reached through a.
+        let project = repo.path().join("project.xml");
+        let deps = [dependency("g:a:1", ""), dependency("g:c:2", "")].concat();
+        fs::write(
+            &project,
+            project_xml(
+                &Coordinate::new("p", "p", "1"),
+                &format!("<dependencies>{}</dependencies>", deps),
+            ),
+        )
+        .unwrap();
+        let resolved = Resolver::new(repo.path())
+            .resolve_project(&project)
+            .unwrap();
+        assert_eq!(
+            resolved_versions(&resolved),
+            vec![
+                ("g:a:1".to_string(), "compile".to_string()),
+                ("g:c:2".to_string(), "compile".to_string()),
+            ]
+        );
+
+        // At equal depth, the first declaration wins and scopes carry over.
+        let deps = [
+            dependency("g:a:1", "<scope>test</scope>"),
+            dependency("g:b:1", ""),
+        ]
+        .concat();
+        fs::write(
+            &project,
+            project_xml(
+                &Coordinate::new("p", "p", "1"),
+                &format!("<dependencies>{}</dependencies>", deps),
+            ),
+        )
+        .unwrap();
+        let resolved = Resolver::new(repo.path())
+            .resolve_project(&project)
+            .unwrap();
+        assert_eq!(
+            resolved_versions(&resolved),
+            vec![
+                ("g:a:1".to_string(), "test".to_string()),
+                ("g:b:1".to_string(), "compile".to_string()),
+                ("g:c:1".to_string(), "test".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn cyclic_bom_imports_fail_instead_of_recursing() {
+        let repo = tempfile::tempdir().unwrap();
+        let import = |coordinate: &str, managed: &str| {
+            format!(
+                "<dependencyManagement><dependencies>{}{}</dependencies></dependencyManagement>",
+                dependency(coordinate, "<type>pom</type><scope>import</scope>"),
+                dependency(managed, "")
+            )
+        };
+        install(repo.path(), "g:bom-a:1", &import("g:bom-b:1", "g:x:1"));
+        install(repo.path(), "g:bom-b:1", &import("g:bom-a:1", "g:y:2"));
+
+        let mut resolver = Resolver::new(repo.path());
+        let effective = resolver
+            .effective_pom_for(&Coordinate::new("g", "bom-a", "1"))
+            .unwrap();
+        let mut managed: Vec<String> = effective
+            .dependency_management
+            .values()
+            .map(|dep| format!("{}:{}", dep.artifact_id, dep.version.clone().unwrap()))
+            .collect();
+        managed.sort();
+        assert_eq!(managed, vec!["x:1", "y:2"]);
+
+        resolver
+            .in_progress
+            .insert(Coordinate::new("g", "bom-c", "1"));
+        assert!(resolver
+            .effective_pom_for(&Coordinate::new("g", "bom-c", "1"))
+            .is_err());
+    }
+
+    #[test]
+    fn empty_relative_path_skips_the_local_parent() {
+        let repo = tempfile::tempdir().unwrap();
+        install(
+            repo.path(),
+            "g:parent:1",
+            "<properties><dep.version>1</dep.version></properties>",
+        );
+        let checkout = tempfile::tempdir().unwrap();
+        let local_parent = project_xml(
+            &Coordinate::new("g", "parent", "1"),
+            "<properties><dep.version>local</dep.version></properties>",
+        );
+        fs::write(checkout.path().join("pom.xml"), local_parent).unwrap();
+        let module_dir = checkout.path().join("module");
+        fs::create_dir(&module_dir).unwrap();
+
+        let child = |relative_path: &str| {
+            format!(
+                "<project><parent><groupId>g</groupId><artifactId>parent</artifactId><version>1</version>{}</parent><artifactId>child</artifactId><dependencies>{}</dependencies></project>",
+                relative_path,
+                dependency("g:d:${dep.version}", "")
+            )
+        };
+        let mut resolver = Resolver::new(repo.path());
+        let module_pom = module_dir.join("pom.xml");
+        for (relative_path, expected) in [("", "local"), ("<relativePath/>", "1")] {
+            fs::write(&module_pom, child(relative_path)).unwrap();
+            let pom = Pom::from_file(&module_pom).unwrap();
+            let effective = resolver.effective_pom(&pom, Some(&module_pom)).unwrap();
+            assert_eq!(effective.dependencies[0].version.as_deref(), Some(expected));
+        }
+    }
+}
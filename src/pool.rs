@@ -0,0 +1,160 @@
+#![allow(unused_imports)]
+#![allow(unused_variables)]
+
+use std::sync::mpsc;
+use std::sync::Mutex;
+use std::thread;
+
+/// Results a worker may get ahead of the writer by, per worker.
+const RESULTS_IN_FLIGHT_PER_WORKER: usize = 4;
+
+fn worker_count() -> usize {
+    thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+}
+
+/// Runs `work` over `items` on one worker thread per core and hands each result to `write` on
+/// the calling thread, in whatever order they finish. Each worker builds its own state with
+/// `init`, for things like parsers that cannot be shared. Only the caller touches `write`, so it
+/// can own the index writes without any locking.
+pub fn for_each_parallel<I, S, R>(
+    items: Vec<I>,
+    init: impl Fn() -> S + Sync,
+    work: impl Fn(&mut S, I) -> R + Sync,
+    mut write: impl FnMut(R),
+) where
+    I: Send,
+    R: Send,
+{
+    let workers = worker_count().min(items.len()).max(1);
+    let queue = Mutex::new(items.into_iter());
+    let (tx, rx) = mpsc::sync_channel::<R>(workers * RESULTS_IN_FLIGHT_PER_WORKER);
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            let tx = tx.clone();
+            let (queue, init, work) = (&queue, &init, &work);
+            scope.spawn(move || {
+                let mut state = init();
+                loop {
+                    // The lock guard has to drop before the work starts.
+                    let next = queue.lock().unwrap().next();
+                    match next {
+                        Some(item) => {
+                            if tx.send(work(&mut state, item)).is_err() {
+                                break;
+                            }
+                        }
+                        None => break,
+                    }
+                }
+            });
+        }
+        drop(tx);
+
+        for result in rx {
+            write(result);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_result_reaches_the_writer_once() {
+        let mut results: Vec<usize> = Vec::new();
+        for_each_parallel((0..500).collect(), || (), |_, n| n * 2, |n| results.push(n));
+        results.sort();
+        assert_eq!(results, (0..500).map(|n| n * 2).collect::<Vec<usize>>());
+    }
+
+    #[test]
+    fn results_arrive_in_the_order_they_finish() {
+        let mut results: Vec<usize> = Vec::new();
+        for_each_parallel(
+            (0..4).collect(),
+            || (),
+            |_, n: usize| {
+                if n == 0 {
+                    thread::sleep(std::time::Duration::from_millis(200));
+                }
+                n
+            },
+            |n| results.push(n),
+        );
+        if worker_count() > 1 {
+            assert_eq!(results.last(), Some(&0));
+        } else {
+            assert_eq!(results, vec![0, 1, 2, 3]);
+        }
+    }
+
+    #[test]
+    fn writer_runs_on_the_calling_thread() {
+        let caller = thread::current().id();
+        let mut writes = 0;
+        for_each_parallel(
+            (0..50).collect(),
+            || (),
+            |_, n: usize| (thread::current().id(), n),
+            |(worker, _)| {
+                assert_ne!(worker, caller);
+                assert_eq!(thread::current().id(), caller);
+                writes += 1;
+            },
+        );
+        assert_eq!(writes, 50);
+    }
+
+    #[test]
+    fn failed_items_do_not_stop_the_others() {
+        let mut oks: Vec<usize> = Vec::new();
+        let mut errors: Vec<String> = Vec::new();
+        for_each_parallel(
+            (0..20).collect(),
+            || (),
+            |_, n: usize| {
+                if n.is_multiple_of(5) {
+                    Err(format!("item {}", n))
+                } else {
+                    Ok(n)
+                }
+            },
+            |result| match result {
+                Ok(n) => oks.push(n),
+                Err(e) => errors.push(e),
+            },
+        );
+        errors.sort();
+        assert_eq!(errors, vec!["item 0", "item 10", "item 15", "item 5"]);
+        assert_eq!(oks.len(), 16);
+    }
+
+    #[test]
+    fn each_worker_keeps_its_own_state() {
+        let mut counts: Vec<usize> = Vec::new();
+        for_each_parallel(
+            (0..100).collect(),
+            || 0,
+            |seen: &mut usize, _: usize| {
+                *seen += 1;
+                *seen
+            },
+            |seen| counts.push(seen),
+        );
+        // Each worker counts from one, so the per-worker counts never exceed the items.
+        assert_eq!(counts.len(), 100);
+        assert!(counts.iter().all(|&seen| (1..=100).contains(&seen)));
+        assert!(counts.contains(&1));
+    }
+
+    #[test]
+    fn no_items_means_no_writes() {
+        let mut writes = 0;
+        for_each_parallel(Vec::<usize>::new(), || (), |_, n| n, |_| writes += 1);
+        assert_eq!(writes, 0);
+    }
+}
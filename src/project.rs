@@ -1,18 +1,27 @@
 #![allow(unused_imports)]
 #![allow(unused_variables)]
 
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::io::Read;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use anyhow::{Error, Result};
+use ignore::gitignore::Gitignore;
+use ignore::{Match, WalkBuilder};
 use tree_sitter::{Language, Node, Parser, TreeCursor};
-use walkdir::{DirEntry, WalkDir};
+
+use crate::config::{CrawlConfig, CrawlFilter, ProjectConfig};
+use crate::indexes::Scope;
+use crate::pool;
 
 extern "C" {
     fn tree_sitter_java() -> Language;
+    fn tree_sitter_kotlin() -> Language;
 }
 
+/// Walks a parsed file, recording its package and the names it declares.
+type Collector = fn(&String, &mut TreeCursor, &mut DeclaredPackage);
+
 #[derive(Debug, Default)]
 pub struct DeclaredPackage {
     pub name: Option<String>,
@@ -47,39 +56,6 @@ impl DeclaredPackage {
     }
 }
 
-fn print_tree(code: &String, cursor: &mut TreeCursor, indent: usize) {
-    let node = cursor.node();
-    if node.kind() == "local_variable_declaration" || node.kind() == "identifier" {
-        println!(
-            "{}{} [{}]",
-            " ".repeat(indent),
-            node.kind(),
-            text_for_node(code, &node)
-        );
-    } else {
-        println!("{}{}", " ".repeat(indent), node.kind());
-    }
-    if cursor.goto_first_child() {
-        print_tree(code, cursor, indent + 2);
-        cursor.goto_parent();
-    }
-
-    if cursor.goto_next_sibling() {
-        print_tree(code, cursor, indent);
-    }
-}
-
-fn print_tree_from_file(parser: &mut Parser, path: &Path) -> Result<()> {
-    let code = std::fs::read_to_string(path)?;
-    let tree = parser
-        .parse(&code, None)
-        .ok_or_else(|| Error::msg("Could not parse."))?;
-
-    print_tree(&code, &mut tree.walk(), 0);
-
-    Ok(())
-}
-
 fn text_for_node(code: &String, node: &Node) -> String {
     match node.utf8_text(code.as_bytes()) {
         Ok(t) => t.to_string(),
@@ -126,9 +102,47 @@ fn collect_identifier(code: &String, cursor: &mut TreeCursor, accum: &mut String
     }
 }
 
+/// Java declarations that introduce a type.
+const JAVA_TYPE_DECLARATIONS: [&str; 5] = [
+    "class_declaration",
+    "interface_declaration",
+    "enum_declaration",
+    "record_declaration",
+    "annotation_type_declaration",
+];
+
+/// Nodes whose contents can only declare local or anonymous types, which cannot be imported.
+const JAVA_LOCAL_SCOPES: [&str; 6] = [
+    "block",
+    "constructor_body",
+    "field_declaration",
+    "constant_declaration",
+    "enum_constant",
+    "static_initializer",
+];
+
+/// Names a declaration found inside the types in `enclosing` by its qualified name.
+fn qualified_name(enclosing: &[String], name: String) -> String {
+    if enclosing.is_empty() {
+        name
+    } else {
+        format!("{}.{}", enclosing.join("."), name)
+    }
+}
+
 fn collect_from_tree(code: &String, cursor: &mut TreeCursor, accum: &mut DeclaredPackage) {
+    collect_java_declarations(code, cursor, &mut Vec::new(), accum);
+}
+
+fn collect_java_declarations(
+    code: &String,
+    cursor: &mut TreeCursor,
+    enclosing: &mut Vec<String>,
+    accum: &mut DeclaredPackage,
+) {
     let node = cursor.node();
 
+    let mut entered_type = false;
     if node.kind() == "package_declaration" {
         let mut identifier: String = String::new();
         collect_identifier(code, cursor, &mut identifier);
@@ -138,83 +152,349 @@ fn collect_from_tree(code: &String, cursor: &mut TreeCursor, accum: &mut Declare
             accum.set_package_name(identifier.clone());
         }
         return;
-    } else if node.kind() == "class_declaration"
-        || node.kind() == "enum_declaration"
-        || node.kind() == "interface_declaration"
-    {
-        let mut identifier: String = String::new();
-        collect_identifier(code, cursor, &mut identifier);
-        if identifier.is_empty() {
-            eprintln!("No identifier found in {}", node.kind());
-        } else {
-            accum.add_class_name(identifier.clone());
+    } else if JAVA_TYPE_DECLARATIONS.contains(&node.kind()) {
+        match node.child_by_field_name("name") {
+            Some(name_node) => {
+                let name = qualified_name(enclosing, text_for_node(code, &name_node));
+                accum.add_class_name(name);
+                enclosing.push(text_for_node(code, &name_node));
+                entered_type = true;
+            }
+            None => eprintln!("No identifier found in {}", node.kind()),
         }
     }
 
-    if cursor.goto_first_child() {
-        collect_from_tree(code, cursor, accum);
+    if !JAVA_LOCAL_SCOPES.contains(&node.kind()) && cursor.goto_first_child() {
+        collect_java_declarations(code, cursor, enclosing, accum);
         cursor.goto_parent();
     }
+    if entered_type {
+        enclosing.pop();
+    }
 
     if cursor.goto_next_sibling() {
-        collect_from_tree(code, cursor, accum);
+        collect_java_declarations(code, cursor, enclosing, accum);
     }
 }
 
-fn collect_from_file(parser: &mut Parser, path: &Path) -> Result<DeclaredPackage> {
-    let code = std::fs::read_to_string(path)?;
+/// Names the first child of `node` of the given kind, such as the `type_identifier` of a Kotlin
+/// class declaration.
+fn child_text_of_kind(code: &String, node: &Node, kind: &str) -> Option<String> {
+    let mut cursor = node.walk();
+    let child = node.children(&mut cursor).find(|c| c.kind() == kind)?;
+    Some(text_for_node(code, &child))
+}
+
+/// Nodes whose contents can only declare local or anonymous Kotlin types. Companion objects are
+/// skipped too since types nested in them are named through the companion.
+const KOTLIN_LOCAL_SCOPES: [&str; 4] = [
+    "function_body",
+    "property_declaration",
+    "anonymous_initializer",
+    "companion_object",
+];
+
+/// The Kotlin counterpart of `collect_from_tree`. Classes, interfaces, objects and typealiases
+/// are named like Java types, nested ones by their qualified names; functions only at the top
+/// level, where they can be imported.
+fn collect_from_kotlin_tree(code: &String, cursor: &mut TreeCursor, accum: &mut DeclaredPackage) {
+    collect_kotlin_declarations(code, cursor, &mut Vec::new(), accum);
+}
+
+fn collect_kotlin_declarations(
+    code: &String,
+    cursor: &mut TreeCursor,
+    enclosing: &mut Vec<String>,
+    accum: &mut DeclaredPackage,
+) {
+    let node = cursor.node();
+    let is_top_level = node
+        .parent()
+        .map(|p| p.kind() == "source_file")
+        .unwrap_or(false);
+
+    let mut entered_type = false;
+    let mut descend = !KOTLIN_LOCAL_SCOPES.contains(&node.kind());
+    match node.kind() {
+        "package_header" => {
+            match child_text_of_kind(code, &node, "identifier") {
+                Some(identifier) => {
+                    accum.set_package_name(identifier.split_whitespace().collect());
+                }
+                None => eprintln!("No identifier found in {}", node.kind()),
+            }
+            descend = false;
+        }
+        // Interfaces are class declarations with the `interface` keyword.
+        "class_declaration" | "object_declaration" | "type_alias" => {
+            if let Some(name) = child_text_of_kind(code, &node, "type_identifier") {
+                accum.add_class_name(qualified_name(enclosing, name.clone()));
+                enclosing.push(name);
+                entered_type = true;
+            }
+        }
+        "function_declaration" if is_top_level => {
+            if let Some(name) = child_text_of_kind(code, &node, "simple_identifier") {
+                accum.add_class_name(name);
+            }
+        }
+        _ => {}
+    };
+
+    if descend && cursor.goto_first_child() {
+        collect_kotlin_declarations(code, cursor, enclosing, accum);
+        cursor.goto_parent();
+    }
+    if entered_type {
+        enclosing.pop();
+    }
+
+    if cursor.goto_next_sibling() {
+        collect_kotlin_declarations(code, cursor, enclosing, accum);
+    }
+}
+
+fn collect_from_code(
+    parser: &mut Parser,
+    collect: Collector,
+    path: &Path,
+    code: &String,
+) -> Result<DeclaredPackage> {
     let tree = parser
-        .parse(&code, None)
+        .parse(code, None)
         .ok_or_else(|| Error::msg("Could not parse."))?;
 
     let mut result = DeclaredPackage::default();
-    collect_from_tree(&code, &mut tree.walk(), &mut result);
-    if result.name.is_some() {
-        if !result.contained_identifiers.is_empty() {
-            result.add_file_name(path.to_str().unwrap().to_string());
-        }
-        Ok(result)
-    } else {
-        Err(Error::msg(format!(
-            "No package declared in {}",
-            path.to_str().unwrap()
-        )))
+    collect(code, &mut tree.walk(), &mut result);
+    if result.name.is_none() {
+        // Files without a package declaration belong to the unnamed package.
+        result.set_package_name(String::new());
+    }
+    if !result.contained_identifiers.is_empty() {
+        result.add_file_name(path.to_str().unwrap().to_string());
     }
+    Ok(result)
 }
 
-fn should_skip(entry: &DirEntry) -> bool {
-    entry
+/// Directories build tools write their output to.
+const BUILD_OUTPUT_DIRS: [&str; 2] = ["target", "build"];
+
+/// Files marking the directory a build tool runs in.
+const BUILD_FILES: [&str; 6] = [
+    "pom.xml",
+    "build.gradle",
+    "build.gradle.kts",
+    "settings.gradle",
+    "settings.gradle.kts",
+    "build.xml",
+];
+
+/// Tests whether a directory holds build output. A `target` or `build` directory only counts
+/// at the project root or beside a build file, so that packages with those names are still
+/// crawled.
+fn is_build_output(dir: &Path, project_root: &Path) -> bool {
+    let is_output_name = dir
         .file_name()
-        .to_str()
-        .map(|s| s.starts_with('.') || s == "generated")
-        .unwrap_or(false)
+        .and_then(|name| name.to_str())
+        .map(|name| BUILD_OUTPUT_DIRS.contains(&name))
+        .unwrap_or(false);
+    match dir.parent() {
+        Some(parent) if is_output_name => {
+            parent == project_root || BUILD_FILES.iter().any(|f| parent.join(f).is_file())
+        }
+        _ => false,
+    }
 }
 
-pub fn crawl_project(path: &Path) -> Result<Vec<DeclaredPackage>> {
-    let mut parser = Parser::new();
-    let lang = unsafe { tree_sitter_java() };
-    parser.set_language(lang)?;
+/// Hidden directories are left to the walker, which skips them along with ignored paths.
+fn should_skip(dir: &Path, project_root: &Path) -> bool {
+    dir.file_name().and_then(|name| name.to_str()) == Some("generated")
+        || is_build_output(dir, project_root)
+}
 
-    let mut packages = HashMap::<String, DeclaredPackage>::new();
+const JAVA_SUFFIX: &str = "java";
+const KOTLIN_SUFFIX: &str = "kt";
 
-    let java_suffix = "java";
-    for e in WalkDir::new(path)
-        .into_iter()
-        .filter_entry(|e| !should_skip(e))
-    {
-        // Silently ignore non-java files and files that lack UTF-8 names.
-        let entry_path = e.ok().and_then(|entry| {
-            let ext = entry.path().extension().and_then(|ext| ext.to_str())?;
-            if ext == java_suffix {
-                Some(entry.into_path())
-            } else {
-                None
+/// Tests whether a file is a source file the crawler knows how to parse.
+pub fn is_source_file(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|ext| ext.to_str()),
+        Some(JAVA_SUFFIX) | Some(KOTLIN_SUFFIX)
+    )
+}
+
+/// Tests whether a source set, the directory under `src`, holds test code: `test` itself, or one
+/// such as `integrationTest`, `androidTest` or `testFixtures`.
+fn is_test_source_set(name: &str) -> bool {
+    name.starts_with("test") || name.ends_with("Test")
+}
+
+/// Tells the scope of a source file from the innermost `src/<source set>` in its path, as in
+/// `src/main/java` or `src/test/kotlin`. Files outside such a layout have none.
+pub fn source_scope(path: &Path) -> Option<Scope> {
+    let components: Vec<&std::ffi::OsStr> = path.iter().collect();
+    components
+        .windows(3)
+        .rev()
+        .find(|window| window[0] == "src")
+        .map(|window| match window[1].to_str() {
+            Some(source_set) if is_test_source_set(source_set) => Scope::Test,
+            _ => Scope::Main,
+        })
+}
+
+fn is_source_entry(entry: &ignore::DirEntry) -> bool {
+    entry.file_type().map(|t| t.is_file()).unwrap_or(false) && is_source_file(entry.path())
+}
+
+/// Lists the source files under a project directory. Paths ignored by `.gitignore` or `.ignore`
+/// files are left out, as are hidden directories, build output and directories named
+/// `generated`, except for the generated roots the crawl config names.
+pub fn source_files(project_root: &Path, config: &CrawlConfig) -> Result<Vec<PathBuf>> {
+    let filter = CrawlFilter::new(config)?;
+    let mut files: BTreeSet<PathBuf> = BTreeSet::new();
+
+    let skip_root = project_root.to_path_buf();
+    let walker = WalkBuilder::new(project_root)
+        .require_git(false)
+        .filter_entry(move |e| {
+            let is_dir = e.file_type().map(|t| t.is_dir()).unwrap_or(false);
+            !(is_dir && should_skip(e.path(), &skip_root))
+        })
+        .build();
+    // Silently ignore unreadable entries and files in other languages.
+    for entry in walker.filter_map(|e| e.ok()).filter(is_source_entry) {
+        let rel_path = entry.path().strip_prefix(project_root)?;
+        if filter.accepts(rel_path) {
+            files.insert(entry.into_path());
+        }
+    }
+
+    for generated_root in config.generated_roots.iter() {
+        let walker = WalkBuilder::new(project_root.join(generated_root))
+            .standard_filters(false)
+            .hidden(true)
+            .build();
+        for entry in walker.filter_map(|e| e.ok()).filter(is_source_entry) {
+            let rel_path = entry.path().strip_prefix(project_root)?;
+            if !filter.excludes(rel_path) {
+                files.insert(entry.into_path());
+            }
+        }
+    }
+
+    Ok(files.into_iter().collect())
+}
+
+/// Tests whether a crawl of the project at `project_root` would pick up `path`, by the rules of
+/// `source_files` but without walking the project. Ignore files are read from each directory
+/// between the root and the file, the deepest and `.ignore` ones taking precedence.
+pub fn is_crawled_file(project_root: &Path, config: &CrawlConfig, path: &Path) -> Result<bool> {
+    let rel_path = match path.strip_prefix(project_root) {
+        Ok(rel_path) => rel_path,
+        Err(_) => return Ok(false),
+    };
+    if !is_source_file(path) {
+        return Ok(false);
+    }
+    let filter = CrawlFilter::new(config)?;
+    let is_generated = config
+        .generated_roots
+        .iter()
+        .any(|root| rel_path.starts_with(root));
+    if is_generated {
+        let is_hidden = rel_path
+            .iter()
+            .any(|name| name.to_string_lossy().starts_with('.'));
+        return Ok(!is_hidden && !filter.excludes(rel_path));
+    }
+    if !filter.accepts(rel_path) {
+        return Ok(false);
+    }
+
+    let names: Vec<&std::ffi::OsStr> = rel_path.iter().collect();
+    let mut ignore_files: Vec<Gitignore> = Vec::new();
+    let mut dir = project_root.to_path_buf();
+    for (i, name) in names.iter().enumerate() {
+        for ignore_name in [".gitignore", ".ignore"] {
+            let ignore_path = dir.join(ignore_name);
+            if ignore_path.is_file() {
+                ignore_files.push(Gitignore::new(ignore_path).0);
             }
-        });
+        }
+        let child = dir.join(name);
+        let is_dir = i + 1 < names.len();
+        if name.to_string_lossy().starts_with('.') || (is_dir && should_skip(&child, project_root))
+        {
+            return Ok(false);
+        }
+        let ignored = ignore_files
+            .iter()
+            .rev()
+            .map(|ignore_file| ignore_file.matched(&child, is_dir))
+            .find(|m| !m.is_none())
+            .map(|m| m.is_ignore())
+            .unwrap_or(false);
+        if ignored {
+            return Ok(false);
+        }
+        dir = child;
+    }
+    Ok(true)
+}
+
+/// The tree-sitter parsers for each source language, kept across files so each is only set up
+/// once per crawl.
+pub struct SourceParser {
+    java: Parser,
+    kotlin: Parser,
+}
+
+impl SourceParser {
+    /// Fails when a grammar cannot be loaded, such as one generated for a tree-sitter ABI this
+    /// build does not support, so that a crawl never silently leaves out a language.
+    pub fn new() -> Result<Self> {
+        let mut java = Parser::new();
+        java.set_language(unsafe { tree_sitter_java() })
+            .map_err(|e| Error::msg(format!("The Java grammar failed to load: {}", e)))?;
+        let mut kotlin = Parser::new();
+        kotlin
+            .set_language(unsafe { tree_sitter_kotlin() })
+            .map_err(|e| Error::msg(format!("The Kotlin grammar failed to load: {}", e)))?;
+        Ok(SourceParser { java, kotlin })
+    }
+
+    /// Collects the package and declarations of one source file from its contents.
+    pub fn collect(&mut self, path: &Path, code: &String) -> Result<DeclaredPackage> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(JAVA_SUFFIX) => collect_from_code(&mut self.java, collect_from_tree, path, code),
+            Some(KOTLIN_SUFFIX) => {
+                collect_from_code(&mut self.kotlin, collect_from_kotlin_tree, path, code)
+            }
+            _ => Err(Error::msg(format!(
+                "Not a source file: {}",
+                path.to_str().unwrap_or_default()
+            ))),
+        }
+    }
+}
 
-        if let Some(path) = entry_path {
-            let _: Result<_> = print_tree_from_file(&mut parser, &path);
-            if let Ok(mut pkg) = collect_from_file(&mut parser, &path) {
+pub fn crawl_project(path: &Path) -> Result<Vec<DeclaredPackage>> {
+    let config = ProjectConfig::load(path)?;
+    let mut packages = HashMap::<String, DeclaredPackage>::new();
+    // Workers set up their own parsers. One made here fails the crawl up front if they can't.
+    SourceParser::new()?;
+
+    pool::for_each_parallel(
+        source_files(path, &config.crawl)?,
+        SourceParser::new,
+        |parser, path| {
+            let parser = parser.as_mut().map_err(|e| Error::msg(e.to_string()))?;
+            let code = std::fs::read_to_string(&path)?;
+            parser.collect(&path, &code)
+        },
+        |collected| {
+            if let Ok(mut pkg) = collected {
                 if let Some(pkg_name) = pkg.name.as_ref().cloned() {
                     if let Some(prev) = packages.get(&pkg_name) {
                         pkg.accum(prev);
@@ -222,14 +502,166 @@ pub fn crawl_project(path: &Path) -> Result<Vec<DeclaredPackage>> {
                     packages.insert(pkg_name.clone(), pkg);
                 }
             }
-        }
-    }
-
-    // for (pkg_name, pkg) in packages {
-    //     println!("{:?}", pkg);
-    // }
+        },
+    );
 
     let result: Vec<DeclaredPackage> = packages.into_values().collect();
 
     Ok(result)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(name)
+    }
+
+    #[test]
+    fn crawl_indexes_kotlin_declarations() {
+        let packages = crawl_project(&fixture("kotlin-project")).unwrap();
+        assert_eq!(packages.len(), 1);
+        assert_eq!(packages[0].name.as_deref(), Some("com.example.shapes"));
+
+        let mut identifiers = packages[0].contained_identifiers.clone();
+        identifiers.sort();
+        assert_eq!(
+            identifiers,
+            vec![
+                "Circle",
+                "Circle.Builder",
+                "Color",
+                "Point",
+                "Registry",
+                "Shape",
+                "ShapeList",
+                "unitCircle",
+            ]
+        );
+    }
+
+    fn collect_java(code: &str) -> DeclaredPackage {
+        SourceParser::new()
+            .unwrap()
+            .collect(Path::new("Source.java"), &code.to_string())
+            .unwrap()
+    }
+
+    #[test]
+    fn java_records_and_annotation_types_are_declarations() {
+        let package = collect_java(
+            "package com.example.model;\n\
+             public record Point(int x, int y) {\n\
+                 public Point { if (x < 0) throw new IllegalArgumentException(); }\n\
+             }\n\
+             @interface Audited { String value() default \"\"; }\n",
+        );
+        assert_eq!(package.name.as_deref(), Some("com.example.model"));
+        assert_eq!(package.contained_identifiers, vec!["Point", "Audited"]);
+    }
+
+    #[test]
+    fn java_nested_types_are_named_through_their_enclosing_types() {
+        let package = collect_java(
+            "package com.example;\n\
+             public class Outer {\n\
+                 public static class Inner {\n\
+                     enum Mode { ON, OFF }\n\
+                 }\n\
+                 interface Callback { record Event(String name) {} }\n\
+                 void run() { class Local {} }\n\
+                 Runnable task = new Runnable() { public void run() {} };\n\
+             }\n",
+        );
+        assert_eq!(
+            package.contained_identifiers,
+            vec![
+                "Outer",
+                "Outer.Inner",
+                "Outer.Inner.Mode",
+                "Outer.Callback",
+                "Outer.Callback.Event",
+            ]
+        );
+    }
+
+    #[test]
+    fn java_default_package_files_belong_to_the_unnamed_package() {
+        let package = collect_java("class Main { public static void main(String[] a) {} }\n");
+        assert_eq!(package.name.as_deref(), Some(""));
+        assert_eq!(package.contained_identifiers, vec!["Main"]);
+        assert_eq!(package.files, vec!["Source.java"]);
+    }
+
+    #[test]
+    fn crawled_file_check_agrees_with_the_walk() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = std::fs::canonicalize(dir.path()).unwrap();
+        let files = [
+            "src/main/java/a/Kept.java",
+            "src/main/java/a/Ignored.java",
+            "src/main/java/a/Excluded.java",
+            "src/main/java/ignored_dir/Inner.java",
+            "src/main/java/ignored_dir/Unignored.java",
+            "src/main/java/.hidden/Hidden.java",
+            "src/main/java/generated/Gen.java",
+            "target/classes/Output.java",
+            "target/gen-src/Wanted.java",
+            "sub/build/Output.java",
+            "other/build/Kept.java",
+            "notes/Readme.txt",
+        ];
+        for file in files {
+            let path = root.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "package a; class A {}").unwrap();
+        }
+        std::fs::write(root.join("sub/pom.xml"), "<project/>").unwrap();
+        std::fs::write(root.join(".gitignore"), "Ignored.java\nignored_dir/\n").unwrap();
+        std::fs::write(root.join("src/main/java/.ignore"), "!Unignored.java\n").unwrap();
+        let config = CrawlConfig {
+            exclude: vec!["**/Excluded.java".to_string()],
+            generated_roots: vec!["target/gen-src".to_string()],
+            ..CrawlConfig::default()
+        };
+
+        let crawled = source_files(&root, &config).unwrap();
+        assert!(crawled.contains(&root.join("src/main/java/a/Kept.java")));
+        assert!(crawled.contains(&root.join("target/gen-src/Wanted.java")));
+        for file in files {
+            let path = root.join(file);
+            assert_eq!(
+                is_crawled_file(&root, &config, &path).unwrap(),
+                crawled.contains(&path),
+                "{}",
+                file
+            );
+        }
+    }
+
+    #[test]
+    fn source_scope_follows_the_source_set() {
+        let scope = |path: &str| source_scope(Path::new(path));
+        assert_eq!(
+            scope("/p/src/main/java/com/example/App.java"),
+            Some(Scope::Main)
+        );
+        assert_eq!(
+            scope("/p/src/test/kotlin/com/example/AppTest.kt"),
+            Some(Scope::Test)
+        );
+        assert_eq!(
+            scope("/p/src/integrationTest/java/com/example/AppIT.java"),
+            Some(Scope::Test)
+        );
+        assert_eq!(
+            scope("/p/src/test/resources/src/main/java/Sample.java"),
+            Some(Scope::Main)
+        );
+        assert_eq!(scope("/p/app/com/example/App.java"), None);
+        assert_eq!(scope("/p/src/App.java"), None);
+    }
+}
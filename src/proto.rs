@@ -2,54 +2,119 @@
 #![allow(unused_variables)]
 
 use std::collections::HashMap;
+use std::fmt;
 use std::fmt::Display;
 use std::io;
 use std::io::{Read, Write};
 use std::os::unix::net::UnixStream;
 use std::path::Path;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
+use std::time::{Duration, Instant};
 
-use anyhow::{bail, Error, Result};
+use anyhow::{bail, Context, Error, Result};
 use serde_derive::Serialize;
 
 extern crate serde_derive;
 extern crate serde_json;
 extern crate sled;
 
+use crate::channel::{Frame, FrameReader};
+use crate::config::{ConfiguredProject, ProjectConfig, PROJECT_CONFIG_FILE};
 use crate::indexes;
-use crate::indexes::Index;
+use crate::indexes::{Index, ProgressFn, ReindexProgress, Scope, SourceLocation};
 use crate::jdk::is_jimage_file;
+use crate::jobs::{JobRegistry, JobStatus};
+use crate::maven;
+use crate::project;
 
+/// Queries name the indexes to consult, or else give a `file_path` from which the project's
+/// `.cpid.toml` is found and its indexes used.
 #[derive(Debug, PartialEq, serde_derive::Deserialize)]
 pub struct ClassQueryArgs {
-    index_name: String,
+    index_name: Option<String>,
     class_name: String,
+    #[serde(default)]
+    include_unexported: bool,
+    /// The file the query is made from, which limits project types to its build module's view.
+    file_path: Option<String>,
+    /// The scope of the code the query is made from, when not told by `file_path`.
+    scope: Option<Scope>,
 }
 
 #[derive(Debug, PartialEq, serde_derive::Deserialize)]
 pub struct ClassMultiQueryArgs {
-    index_names: Vec<String>,
+    index_names: Option<Vec<String>>,
+    class_name: String,
+    #[serde(default)]
+    include_unexported: bool,
+    file_path: Option<String>,
+    scope: Option<Scope>,
+}
+
+#[derive(Debug, PartialEq, serde_derive::Deserialize)]
+pub struct ClassSourceQueryArgs {
+    index_name: Option<String>,
+    class_name: String,
+    file_path: Option<String>,
+}
+
+#[derive(Debug, PartialEq, serde_derive::Deserialize)]
+pub struct ClassSourceMultiQueryArgs {
+    index_names: Option<Vec<String>>,
+    class_name: String,
+    file_path: Option<String>,
+}
+
+#[derive(Debug, PartialEq, serde_derive::Deserialize)]
+pub struct CoordinateQueryArgs {
+    index_name: Option<String>,
     class_name: String,
+    file_path: Option<String>,
 }
 
 #[derive(Debug, PartialEq, serde_derive::Deserialize)]
 pub struct ReindexArgs {
     index_name: String,
     archive_source: String,
+    /// The dependency scope of a reindexed classpath.
+    scope: Option<Scope>,
+}
+
+/// Names a project file that was saved, or whose unsaved buffer is given as `contents`.
+#[derive(Debug, PartialEq, serde_derive::Deserialize)]
+pub struct ProjectFileChangedArgs {
+    /// Defaults to the sources index of the `.cpid.toml` above `path`.
+    index_name: Option<String>,
+    path: String,
+    contents: Option<String>,
 }
 
 #[derive(Debug, PartialEq, serde_derive::Deserialize)]
 pub struct PackageEnumerateArgs {
-    index_name: String,
+    index_name: Option<String>,
     package_name: String,
+    file_path: Option<String>,
 }
 
 #[derive(Debug, PartialEq, serde_derive::Deserialize)]
 pub struct PackageMultiEnumerateArgs {
-    index_names: Vec<String>,
+    index_names: Option<Vec<String>>,
     package_name: String,
+    file_path: Option<String>,
+}
+
+/// Asks after one background job, or after all of them when no id is given.
+#[derive(Debug, PartialEq, serde_derive::Deserialize)]
+pub struct JobStatusQueryArgs {
+    job_id: Option<u64>,
+}
+
+/// Names a path in a project whose `.cpid.toml` says what to reindex.
+#[derive(Debug, PartialEq, serde_derive::Deserialize)]
+pub struct ReindexConfiguredArgs {
+    file_path: String,
 }
 
 #[derive(Debug, PartialEq, serde_derive::Deserialize)]
@@ -57,20 +122,63 @@ pub struct PackageMultiEnumerateArgs {
 pub enum ClientMsg {
     ClassQuery(ClassQueryArgs),
     ClassMultiQuery(ClassMultiQueryArgs),
+    ClassSourceQuery(ClassSourceQueryArgs),
+    ClassSourceMultiQuery(ClassSourceMultiQueryArgs),
+    CoordinateQuery(CoordinateQueryArgs),
     PackageEnumerateQuery(PackageEnumerateArgs),
     PackageMultiEnumerateQuery(PackageMultiEnumerateArgs),
     ReindexPathCmd(ReindexArgs),
     ReindexClasspathCmd(ReindexArgs),
     ReindexProjectCmd(ReindexArgs),
+    ReindexPomCmd(ReindexArgs),
+    ReindexConfiguredCmd(ReindexConfiguredArgs),
+    ProjectFileChangedCmd(ProjectFileChangedArgs),
+    JobStatusQuery(JobStatusQueryArgs),
     ShutdownCmd,
+    /// Any message type not listed above.
+    #[serde(other)]
+    Unsupported,
 }
 
 #[derive(Debug, PartialEq, Serialize)]
 pub struct ClassQueryResponseArgs {
     pub results: HashMap<String, Vec<String>>,
+    /// The JPMS module of each resulting package that belongs to one.
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub modules: HashMap<String, String>,
 }
 
 impl ClassQueryResponseArgs {
+    pub fn new(results: HashMap<String, Vec<String>>) -> Self {
+        Self {
+            results,
+            modules: HashMap::new(),
+        }
+    }
+
+    pub fn with_modules(mut self, modules: HashMap<String, String>) -> Self {
+        self.modules.extend(modules);
+        self
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct ClassSourceQueryResponseArgs {
+    pub results: HashMap<String, Vec<SourceLocation>>,
+}
+
+impl ClassSourceQueryResponseArgs {
+    pub fn new(results: HashMap<String, Vec<SourceLocation>>) -> Self {
+        Self { results }
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct CoordinateQueryResponseArgs {
+    pub results: HashMap<String, Vec<String>>,
+}
+
+impl CoordinateQueryResponseArgs {
     pub fn new(results: HashMap<String, Vec<String>>) -> Self {
         Self { results }
     }
@@ -87,62 +195,289 @@ impl PackageEnumerateQueryResponseArgs {
     }
 }
 
+/// What kind of failure an `ErrorResponse` reports.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub enum ErrorKind {
+    /// The message's type is not one the server handles.
+    UnsupportedMessage,
+    /// The message could not be read, or its arguments do not make a request.
+    InvalidRequest,
+    /// A path the message names is missing or not of a kind the request can use.
+    InvalidPath,
+    /// The request was understood but carrying it out failed.
+    Failed,
+}
+
+/// A failure whose kind is known where it is raised. Other errors are reported as `Failed`, or
+/// as `InvalidPath` when they come down to a missing file.
+#[derive(Debug)]
+pub struct RequestError {
+    kind: ErrorKind,
+    message: String,
+    detail: Option<String>,
+}
+
+impl RequestError {
+    pub fn new(kind: ErrorKind, message: String) -> Self {
+        RequestError {
+            kind,
+            message,
+            detail: None,
+        }
+    }
+
+    pub fn with_detail(mut self, detail: String) -> Self {
+        self.detail = Some(detail);
+        self
+    }
+}
+
+impl fmt::Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for RequestError {}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ErrorResponseArgs {
+    pub kind: ErrorKind,
+    pub message: String,
+    /// The causes behind the message, outermost first. For an `UnsupportedMessage`, the
+    /// message type the server does not know.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl ErrorResponseArgs {
+    pub fn from_error(e: &Error) -> Self {
+        let request_error = e
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<RequestError>());
+        let kind = match request_error {
+            Some(request_error) => request_error.kind,
+            None if e.chain().any(|cause| {
+                cause
+                    .downcast_ref::<io::Error>()
+                    .map(|io_error| io_error.kind() == io::ErrorKind::NotFound)
+                    .unwrap_or(false)
+            }) =>
+            {
+                ErrorKind::InvalidPath
+            }
+            None => ErrorKind::Failed,
+        };
+        let causes: Vec<String> = e.chain().skip(1).map(|cause| cause.to_string()).collect();
+        Self {
+            kind,
+            message: e.to_string(),
+            detail: request_error
+                .and_then(|request_error| request_error.detail.clone())
+                .or_else(|| (!causes.is_empty()).then(|| causes.join(": "))),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, serde_derive::Serialize)]
 #[serde(tag = "type")]
 pub enum ResponseMsg {
     ClassQueryResponse(ClassQueryResponseArgs),
+    ClassSourceQueryResponse(ClassSourceQueryResponseArgs),
+    CoordinateQueryResponse(CoordinateQueryResponseArgs),
     PackageEnumerateQueryResponse(PackageEnumerateQueryResponseArgs),
+    ErrorResponse(ErrorResponseArgs),
+    /// The answer to a reindex command, whose work goes on in the background.
+    JobStarted(JobStartedArgs),
+    /// Sent unasked, under sequence number 0, as a job gets through its archives.
+    JobProgress(JobStatus),
+    /// Sent unasked, under sequence number 0, once a job is over.
+    JobFinished(JobStatus),
+    JobStatusResponse(JobStatusResponseArgs),
     NullResponse,
 }
 
+#[derive(Debug, PartialEq, Serialize)]
+pub struct JobStartedArgs {
+    pub job_id: u64,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct JobStatusResponseArgs {
+    pub jobs: Vec<JobStatus>,
+}
+
+/// A channel message with its body still unread, so that a body which is not a valid
+/// `ClientMsg` can be answered under the message's sequence number.
 #[derive(Debug, PartialEq, serde_derive::Deserialize)]
-pub struct ChannelMsg(u32, ClientMsg);
+pub struct ChannelMsg(u32, serde_json::Value);
 
 #[derive(Debug, PartialEq, serde_derive::Serialize)]
 pub struct ChannelResponse(u32, ResponseMsg);
 
-fn exec_class_query(db: &sled::Db, msg: ClassQueryArgs) -> Result<ResponseMsg> {
-    let results = Index::new(db, &msg.index_name).query_class_index(&msg.class_name)?;
+/// Names the modules of the packages in a class query result.
+pub fn result_modules(
+    index: &Index,
+    results: &HashMap<String, Vec<String>>,
+) -> Result<HashMap<String, String>> {
+    let packages: Vec<String> = results.values().flatten().cloned().collect();
+    Ok(index
+        .query_package_modules(&packages)?
+        .into_iter()
+        .map(|(package_name, module)| (package_name, module.module))
+        .collect())
+}
+
+/// Names the indexes a query consults: those it names, else those the `.cpid.toml` above its
+/// `file_path` declares.
+fn query_index_names(named: Option<Vec<String>>, file_path: Option<&str>) -> Result<Vec<String>> {
+    match (named, file_path) {
+        (Some(names), _) => Ok(names),
+        (None, Some(file_path)) => Ok(discover_project(Path::new(file_path))?.query_indexes()),
+        (None, None) => Err(RequestError::new(
+            ErrorKind::InvalidRequest,
+            "The query names no index and gives no file_path to find one by.".to_string(),
+        )
+        .into()),
+    }
+}
+
+fn discover_project(path: &Path) -> Result<ConfiguredProject> {
+    ProjectConfig::discover(path)?.ok_or_else(|| {
+        RequestError::new(
+            ErrorKind::InvalidPath,
+            format!(
+                "No {} found in {} or above it.",
+                PROJECT_CONFIG_FILE,
+                path.display()
+            ),
+        )
+        .into()
+    })
+}
+
+fn invalid_path(message: String) -> Error {
+    RequestError::new(ErrorKind::InvalidPath, message).into()
+}
+
+fn merge_results<V>(results: &mut HashMap<String, Vec<V>>, more: HashMap<String, Vec<V>>) {
+    for (key, values) in more {
+        results.entry(key).or_default().extend(values);
+    }
+}
+
+fn class_query(
+    db: &sled::Db,
+    index_names: Vec<String>,
+    class_name: &str,
+    include_unexported: bool,
+    file_path: Option<&str>,
+    scope: Option<Scope>,
+) -> Result<ResponseMsg> {
+    let mut results: HashMap<String, Vec<String>> = HashMap::new();
+    let mut modules: HashMap<String, String> = HashMap::new();
+    for idx_name in index_names {
+        let index = Index::new(db, &idx_name);
+        let filter = index.query_filter(include_unexported, file_path.map(Path::new), scope)?;
+        let results1 = index.query_class_index_with(class_name, &filter)?;
+        modules.extend(result_modules(&index, &results1)?);
+        merge_results(&mut results, results1);
+    }
+
     Ok(ResponseMsg::ClassQueryResponse(
-        ClassQueryResponseArgs::new(results),
+        ClassQueryResponseArgs::new(results).with_modules(modules),
     ))
 }
 
+fn exec_class_query(db: &sled::Db, msg: ClassQueryArgs) -> Result<ResponseMsg> {
+    let index_names = query_index_names(
+        msg.index_name.map(|name| vec![name]),
+        msg.file_path.as_deref(),
+    )?;
+    class_query(
+        db,
+        index_names,
+        &msg.class_name,
+        msg.include_unexported,
+        msg.file_path.as_deref(),
+        msg.scope,
+    )
+}
+
 fn exec_class_multi_query(db: &sled::Db, msg: ClassMultiQueryArgs) -> Result<ResponseMsg> {
-    let mut results: HashMap<String, Vec<String>> = HashMap::new();
-    for idx_name in msg.index_names {
-        let results1 = Index::new(db, &idx_name).query_class_index(&msg.class_name)?;
-        for (rk1, rv1) in results1.into_iter() {
-            let mut rv = results.get(&rk1).unwrap_or(&Vec::new()).clone();
-            rv.extend(rv1);
-            results.insert(rk1, rv);
-        }
+    let index_names = query_index_names(msg.index_names, msg.file_path.as_deref())?;
+    class_query(
+        db,
+        index_names,
+        &msg.class_name,
+        msg.include_unexported,
+        msg.file_path.as_deref(),
+        msg.scope,
+    )
+}
+
+fn class_source_query(
+    db: &sled::Db,
+    index_names: Vec<String>,
+    class_name: &str,
+) -> Result<ResponseMsg> {
+    let mut results: HashMap<String, Vec<SourceLocation>> = HashMap::new();
+    for idx_name in index_names {
+        merge_results(
+            &mut results,
+            Index::new(db, &idx_name).query_source_index(class_name)?,
+        );
     }
 
-    Ok(ResponseMsg::ClassQueryResponse(
-        ClassQueryResponseArgs::new(results),
+    Ok(ResponseMsg::ClassSourceQueryResponse(
+        ClassSourceQueryResponseArgs::new(results),
     ))
 }
 
-fn exec_package_enumerate_query(db: &sled::Db, msg: PackageEnumerateArgs) -> Result<ResponseMsg> {
-    let results = Index::new(db, &msg.index_name).query_package_index(&msg.package_name)?;
-    Ok(ResponseMsg::PackageEnumerateQueryResponse(
-        PackageEnumerateQueryResponseArgs::new(results),
+fn exec_class_source_query(db: &sled::Db, msg: ClassSourceQueryArgs) -> Result<ResponseMsg> {
+    let index_names = query_index_names(
+        msg.index_name.map(|name| vec![name]),
+        msg.file_path.as_deref(),
+    )?;
+    class_source_query(db, index_names, &msg.class_name)
+}
+
+fn exec_class_source_multi_query(
+    db: &sled::Db,
+    msg: ClassSourceMultiQueryArgs,
+) -> Result<ResponseMsg> {
+    let index_names = query_index_names(msg.index_names, msg.file_path.as_deref())?;
+    class_source_query(db, index_names, &msg.class_name)
+}
+
+fn exec_coordinate_query(db: &sled::Db, msg: CoordinateQueryArgs) -> Result<ResponseMsg> {
+    let index_names = query_index_names(
+        msg.index_name.map(|name| vec![name]),
+        msg.file_path.as_deref(),
+    )?;
+    let mut results: HashMap<String, Vec<String>> = HashMap::new();
+    for idx_name in index_names {
+        merge_results(
+            &mut results,
+            Index::new(db, &idx_name).query_coordinate_index(&msg.class_name)?,
+        );
+    }
+    Ok(ResponseMsg::CoordinateQueryResponse(
+        CoordinateQueryResponseArgs::new(results),
     ))
 }
 
-fn exec_package_multi_enumerate_query(
+fn package_enumerate_query(
     db: &sled::Db,
-    msg: PackageMultiEnumerateArgs,
+    index_names: Vec<String>,
+    package_name: &str,
 ) -> Result<ResponseMsg> {
     let mut results: HashMap<String, Vec<String>> = HashMap::new();
-    for idx_name in msg.index_names {
-        let results1 = Index::new(db, &idx_name).query_package_index(&msg.package_name)?;
-        for (rk1, rv1) in results1.into_iter() {
-            let mut rv = results.get(&rk1).unwrap_or(&Vec::new()).clone();
-            rv.extend(rv1);
-            results.insert(rk1, rv);
-        }
+    for idx_name in index_names {
+        merge_results(
+            &mut results,
+            Index::new(db, &idx_name).query_package_index(package_name)?,
+        );
     }
 
     Ok(ResponseMsg::PackageEnumerateQueryResponse(
@@ -150,81 +485,574 @@ fn exec_package_multi_enumerate_query(
     ))
 }
 
-fn exec_reindex_project_cmd(db: &sled::Db, msg: ReindexArgs) -> Result<ResponseMsg> {
-    let proj_path = Path::new(&msg.archive_source);
-    indexes::reindex_project_path(&Index::new(db, &msg.index_name), proj_path)?;
-    Ok(ResponseMsg::NullResponse)
+fn exec_package_enumerate_query(db: &sled::Db, msg: PackageEnumerateArgs) -> Result<ResponseMsg> {
+    let index_names = query_index_names(
+        msg.index_name.map(|name| vec![name]),
+        msg.file_path.as_deref(),
+    )?;
+    package_enumerate_query(db, index_names, &msg.package_name)
+}
+
+fn exec_package_multi_enumerate_query(
+    db: &sled::Db,
+    msg: PackageMultiEnumerateArgs,
+) -> Result<ResponseMsg> {
+    let index_names = query_index_names(msg.index_names, msg.file_path.as_deref())?;
+    package_enumerate_query(db, index_names, &msg.package_name)
+}
+
+/// Least time between two progress notifications of a job.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// What the requests of one client connection share.
+struct Connection {
+    db: sled::Db,
+    jobs: Arc<JobRegistry>,
+    /// Replies and notifications for the connection's writer thread.
+    responses: mpsc::Sender<(u32, ResponseMsg)>,
+}
+
+/// Runs a reindex on a thread of its own and answers at once with the job's id. The client is
+/// sent the job's progress as it goes, at most every `PROGRESS_INTERVAL`, and its final status
+/// once it is over. The job first waits for a lease on the indexes it writes, so that it never
+/// runs alongside another update to them.
+fn spawn_reindex_job(
+    conn: &Connection,
+    command: &str,
+    index_names: Vec<String>,
+    reindex: impl FnOnce(&sled::Db, &ProgressFn) -> Result<()> + Send + 'static,
+) -> Result<ResponseMsg> {
+    let job_id = conn.jobs.start(command);
+    let db = conn.db.clone();
+    let jobs = conn.jobs.clone();
+    let responses = conn.responses.clone();
+    thread::spawn(move || {
+        let progress_jobs = jobs.clone();
+        let progress_responses = responses.clone();
+        let last_sent: Mutex<Option<Instant>> = Mutex::new(None);
+        let report = move |progress: &ReindexProgress| {
+            let status = match progress_jobs.update_progress(job_id, progress) {
+                Some(status) => status,
+                None => return,
+            };
+            let mut last_sent = last_sent.lock().unwrap();
+            let is_due = last_sent
+                .map(|sent| sent.elapsed() >= PROGRESS_INTERVAL)
+                .unwrap_or(true);
+            if is_due || progress.archives_done == progress.archives_total {
+                *last_sent = Some(Instant::now());
+                // The client may have gone, but the job carries on regardless.
+                let _ = progress_responses.send((0, ResponseMsg::JobProgress(status)));
+            }
+        };
+        let result = {
+            let _lease = jobs.lease_indexes(&index_names);
+            reindex(&db, &report)
+        };
+        if let Err(e) = &result {
+            eprintln!("ERR: Job {} failed: {}", job_id, e);
+        }
+        if let Some(status) = jobs.finish(job_id, &result) {
+            let _ = responses.send((0, ResponseMsg::JobFinished(status)));
+        }
+    });
+    Ok(ResponseMsg::JobStarted(JobStartedArgs { job_id }))
 }
 
-fn exec_reindex_classpath_cmd(db: &sled::Db, msg: ReindexArgs) -> Result<ResponseMsg> {
-    indexes::reindex_classpath(&Index::new(db, &msg.index_name), &msg.archive_source)?;
+fn exec_reindex_project_cmd(conn: &Connection, msg: ReindexArgs) -> Result<ResponseMsg> {
+    if !Path::new(&msg.archive_source).is_dir() {
+        return Err(invalid_path(format!(
+            "{} is not a directory.",
+            msg.archive_source
+        )));
+    }
+    let index_names = vec![msg.index_name.clone()];
+    spawn_reindex_job(
+        conn,
+        "ReindexProjectCmd",
+        index_names,
+        move |db, progress| {
+            indexes::reindex_project_path(
+                &Index::new(db, &msg.index_name).with_progress(progress),
+                Path::new(&msg.archive_source),
+            )
+        },
+    )
+}
+
+/// Reindexes one project file in place. While a job is updating the same index, whose crawl
+/// would otherwise race with it, the file is instead reindexed in a job of its own once that one
+/// is over, so that the connection is never held up waiting.
+fn exec_project_file_changed_cmd(
+    conn: &Connection,
+    msg: ProjectFileChangedArgs,
+) -> Result<ResponseMsg> {
+    let path = Path::new(&msg.path);
+    if !project::is_source_file(path) {
+        return Err(invalid_path(format!("{} is not a source file.", msg.path)));
+    }
+    let index_name = match msg.index_name {
+        Some(index_name) => index_name,
+        None => discover_project(path)?.sources_index(),
+    };
+    let lease = conn
+        .jobs
+        .try_lease_indexes(std::slice::from_ref(&index_name));
+    if lease.is_none() {
+        let index_names = vec![index_name.clone()];
+        return spawn_reindex_job(
+            conn,
+            "ProjectFileChangedCmd",
+            index_names,
+            move |db, progress| {
+                indexes::reindex_project_file(
+                    &Index::new(db, &index_name),
+                    Path::new(&msg.path),
+                    msg.contents.as_deref(),
+                )
+                .with_context(|| format!("Could not reindex {}", msg.path))
+            },
+        );
+    }
+    indexes::reindex_project_file(
+        &Index::new(&conn.db, &index_name),
+        path,
+        msg.contents.as_deref(),
+    )
+    .with_context(|| format!("Could not reindex {}", msg.path))?;
     Ok(ResponseMsg::NullResponse)
 }
 
-fn exec_reindex_path_cmd(db: &sled::Db, msg: ReindexArgs) -> Result<ResponseMsg> {
+fn exec_reindex_configured_cmd(
+    conn: &Connection,
+    msg: ReindexConfiguredArgs,
+) -> Result<ResponseMsg> {
+    let project = discover_project(Path::new(&msg.file_path))?;
+    let index_names = vec![project.sources_index(), project.dependencies_index()];
+    spawn_reindex_job(
+        conn,
+        "ReindexConfiguredCmd",
+        index_names,
+        move |db, progress| indexes::reindex_configured_project(db, &project, Some(progress)),
+    )
+}
+
+fn exec_reindex_classpath_cmd(conn: &Connection, msg: ReindexArgs) -> Result<ResponseMsg> {
+    let index_names = vec![msg.index_name.clone()];
+    spawn_reindex_job(
+        conn,
+        "ReindexClasspathCmd",
+        index_names,
+        move |db, progress| {
+            indexes::reindex_classpath(
+                &Index::new(db, &msg.index_name).with_progress(progress),
+                &msg.archive_source,
+                msg.scope,
+            )
+        },
+    )
+}
+
+fn exec_reindex_pom_cmd(conn: &Connection, msg: ReindexArgs) -> Result<ResponseMsg> {
+    if !Path::new(&msg.archive_source).is_file() {
+        return Err(invalid_path(format!(
+            "{} is not a file.",
+            msg.archive_source
+        )));
+    }
+    let repo_path = maven::default_local_repository()
+        .ok_or_else(|| anyhow::Error::msg("Cannot locate the local Maven repository."))?;
+    let index_names = vec![msg.index_name.clone()];
+    spawn_reindex_job(conn, "ReindexPomCmd", index_names, move |db, progress| {
+        indexes::reindex_pom(
+            &Index::new(db, &msg.index_name).with_progress(progress),
+            Path::new(&msg.archive_source),
+            &repo_path,
+        )
+    })
+}
+
+fn exec_reindex_path_cmd(conn: &Connection, msg: ReindexArgs) -> Result<ResponseMsg> {
     let path = Path::new(&msg.archive_source);
     let path_str = path.to_str().ok_or_else(|| {
-        anyhow::Error::msg("Invalid archive source path provided in protocol message.")
+        invalid_path("Invalid archive source path provided in protocol message.".to_string())
     })?;
-    if path.is_file() && is_jimage_file(path_str) {
-        indexes::reindex_jimage(&Index::new(db, &msg.index_name), path)?;
-    } else if path.is_dir() {
-        indexes::reindex_jar_dir(&Index::new(db, &msg.index_name), path)?;
+    let is_jimage = path.is_file() && is_jimage_file(path_str);
+    if !is_jimage && !path.is_dir() {
+        return Err(invalid_path(format!(
+            "{} is neither a JDK image nor a directory.",
+            msg.archive_source
+        )));
     }
-    Ok(ResponseMsg::NullResponse)
+    let index_names = vec![msg.index_name.clone()];
+    spawn_reindex_job(conn, "ReindexPathCmd", index_names, move |db, progress| {
+        let index = Index::new(db, &msg.index_name).with_progress(progress);
+        let path = Path::new(&msg.archive_source);
+        if is_jimage {
+            indexes::reindex_jimage(&index, path)
+        } else {
+            indexes::reindex_jar_dir(&index, path)
+        }
+    })
+}
+
+fn exec_job_status_query(conn: &Connection, msg: JobStatusQueryArgs) -> Result<ResponseMsg> {
+    let jobs = match msg.job_id {
+        Some(job_id) => vec![conn.jobs.status(job_id).ok_or_else(|| {
+            RequestError::new(ErrorKind::InvalidRequest, format!("No job {}.", job_id))
+        })?],
+        None => conn.jobs.statuses(),
+    };
+    Ok(ResponseMsg::JobStatusResponse(JobStatusResponseArgs {
+        jobs,
+    }))
+}
+
+/// Reads the body of a channel message. Unknown message types are told apart from known ones
+/// with bad arguments.
+fn read_client_msg(body: serde_json::Value) -> Result<ClientMsg> {
+    let msg_type = body
+        .get("type")
+        .and_then(|t| t.as_str())
+        .unwrap_or_default()
+        .to_string();
+    match serde_json::from_value::<ClientMsg>(body) {
+        Ok(ClientMsg::Unsupported) => Err(RequestError::new(
+            ErrorKind::UnsupportedMessage,
+            format!("Unsupported message type: {}", msg_type),
+        )
+        .with_detail(msg_type)
+        .into()),
+        Ok(client_msg) => Ok(client_msg),
+        Err(e) => Err(RequestError::new(
+            ErrorKind::InvalidRequest,
+            format!("Invalid {} message: {}", msg_type, e),
+        )
+        .into()),
+    }
+}
+
+fn exec_client_msg(conn: &Connection, client_msg: ClientMsg) -> Result<ResponseMsg> {
+    let db = &conn.db;
+    match client_msg {
+        ClientMsg::ClassQuery(args) => exec_class_query(db, args),
+        ClientMsg::ClassMultiQuery(args) => exec_class_multi_query(db, args),
+        ClientMsg::ClassSourceQuery(args) => exec_class_source_query(db, args),
+        ClientMsg::ClassSourceMultiQuery(args) => exec_class_source_multi_query(db, args),
+        ClientMsg::CoordinateQuery(args) => exec_coordinate_query(db, args),
+        ClientMsg::PackageEnumerateQuery(args) => exec_package_enumerate_query(db, args),
+        ClientMsg::PackageMultiEnumerateQuery(args) => exec_package_multi_enumerate_query(db, args),
+        ClientMsg::ReindexClasspathCmd(args) => exec_reindex_classpath_cmd(conn, args),
+        ClientMsg::ReindexPathCmd(args) => exec_reindex_path_cmd(conn, args),
+        ClientMsg::ReindexProjectCmd(args) => exec_reindex_project_cmd(conn, args),
+        ClientMsg::ReindexPomCmd(args) => exec_reindex_pom_cmd(conn, args),
+        ClientMsg::ReindexConfiguredCmd(args) => exec_reindex_configured_cmd(conn, args),
+        ClientMsg::ProjectFileChangedCmd(args) => exec_project_file_changed_cmd(conn, args),
+        ClientMsg::JobStatusQuery(args) => exec_job_status_query(conn, args),
+        ClientMsg::ShutdownCmd | ClientMsg::Unsupported => Err(RequestError::new(
+            ErrorKind::UnsupportedMessage,
+            "Not a request the server answers.".to_string(),
+        )
+        .into()),
+    }
+}
+
+fn write_response<O: Write>(outstream: &mut O, seq: u32, resp_msg: ResponseMsg) -> Result<()> {
+    let s = serde_json::to_string::<ChannelResponse>(&ChannelResponse(seq, resp_msg))?;
+    outstream.write_all(s.as_bytes())?;
+    outstream.flush()?;
+    Ok(())
+}
+
+fn error_response(e: &Error) -> ResponseMsg {
+    eprintln!("ERR: {}", e);
+    ResponseMsg::ErrorResponse(ErrorResponseArgs::from_error(e))
+}
+
+/// Reads the sequence number a malformed message starts with, so that its error can be
+/// answered under it. Returns 0 when there is none to be found.
+fn recover_seq(text: &[u8]) -> u32 {
+    let text = String::from_utf8_lossy(text);
+    text.trim_start()
+        .strip_prefix('[')
+        .map(|rest| {
+            rest.trim_start()
+                .chars()
+                .take_while(char::is_ascii_digit)
+                .collect::<String>()
+        })
+        .and_then(|digits| digits.parse().ok())
+        .unwrap_or(0)
 }
 
-pub fn handle_client<I: Read, O: Write>(
+fn malformed_msg(text: &[u8], reason: String) -> (u32, Result<ClientMsg>) {
+    (
+        recover_seq(text),
+        Err(RequestError::new(ErrorKind::InvalidRequest, reason).into()),
+    )
+}
+
+/// Splits a channel message into its sequence number and request.
+fn read_channel_msg(text: &[u8]) -> (u32, Result<ClientMsg>) {
+    match serde_json::from_slice::<ChannelMsg>(text) {
+        Ok(ChannelMsg(seq, body)) => (seq, read_client_msg(body)),
+        Err(e) if e.is_data() => malformed_msg(
+            text,
+            format!("Not a [sequence number, message] pair: {}", e),
+        ),
+        Err(e) => malformed_msg(text, format!("Malformed message: {}", e)),
+    }
+}
+
+/// Answers each message a client sends under its sequence number, failures included. A
+/// malformed message is answered under the sequence number it starts with, or else 0, which
+/// vim reserves for messages it did not ask for. Reading resumes at the next message.
+///
+/// Reindex commands are answered as soon as their job starts, and the job's notifications
+/// follow. Returns once the client is done and the jobs it started are over.
+pub fn handle_client<I: Read, O: Write + Send>(
     db: sled::Db,
     instream: I,
     mut outstream: O,
     shutdown_cond: Arc<AtomicBool>,
+    jobs: Arc<JobRegistry>,
 ) {
-    let msgs = serde_json::Deserializer::from_reader(io::BufReader::new(instream));
-    for msg in msgs.into_iter::<ChannelMsg>() {
-        match msg {
-            Ok(msg1) => {
-                let resp_msg: Result<ResponseMsg> = match msg1.1 {
-                    ClientMsg::ClassQuery(args) => exec_class_query(&db, args),
-                    ClientMsg::ClassMultiQuery(args) => exec_class_multi_query(&db, args),
-                    ClientMsg::PackageEnumerateQuery(args) => {
-                        exec_package_enumerate_query(&db, args)
-                    }
-                    ClientMsg::PackageMultiEnumerateQuery(args) => {
-                        exec_package_multi_enumerate_query(&db, args)
+    let (responses, to_write) = mpsc::channel::<(u32, ResponseMsg)>();
+    thread::scope(|scope| {
+        // Replies and job notifications go through one thread so that they never interleave.
+        scope.spawn(move || {
+            let mut connected = true;
+            for (seq, resp_msg) in to_write {
+                if connected {
+                    if let Err(e) = write_response(&mut outstream, seq, resp_msg) {
+                        eprintln!("ERR: {}", e);
+                        connected = false;
                     }
-                    ClientMsg::ReindexClasspathCmd(args) => exec_reindex_classpath_cmd(&db, args),
-                    ClientMsg::ReindexPathCmd(args) => exec_reindex_path_cmd(&db, args),
-                    ClientMsg::ReindexProjectCmd(args) => exec_reindex_project_cmd(&db, args),
-                    ClientMsg::ShutdownCmd => {
-                        shutdown_cond.store(true, Ordering::SeqCst);
-                        eprintln!("Client requested shutdown.");
-                        break;
-                    }
-                };
-                let write_result = resp_msg
-                    .and_then(|m| {
-                        serde_json::to_string::<ChannelResponse>(&ChannelResponse(msg1.0, m))
-                            .map_err(anyhow::Error::new)
-                    })
-                    .and_then(|s| {
-                        outstream
-                            .write_all(s.as_bytes())
-                            .and_then(|_| outstream.flush())
-                            .map_err(anyhow::Error::new)
-                    })
-                    .map_err(|e| eprintln!("ERR: {}", e));
-                if let Err(e) = write_result {
-                    break;
                 }
             }
-            Err(e) => {
-                eprintln!("ERR: {}", e);
+            // Ignore this error because we are already done communicating with the client.
+            let _ = outstream.flush();
+        });
+
+        let conn = Connection {
+            db,
+            jobs,
+            responses,
+        };
+        for frame in FrameReader::new(io::BufReader::new(instream)) {
+            let (seq, client_msg) = match frame {
+                Ok(Frame::Value(text)) => read_channel_msg(&text),
+                Ok(Frame::Malformed(text)) => malformed_msg(
+                    &text,
+                    "Malformed message: not a complete JSON array or object.".to_string(),
+                ),
+                Err(e) => {
+                    eprintln!("ERR: {}", e);
+                    break;
+                }
+            };
+            let resp_msg = match client_msg {
+                Ok(ClientMsg::ShutdownCmd) => {
+                    shutdown_cond.store(true, Ordering::SeqCst);
+                    eprintln!("Client requested shutdown.");
+                    break;
+                }
+                Ok(client_msg) => exec_client_msg(&conn, client_msg),
+                Err(e) => Err(e),
+            };
+            let resp_msg = resp_msg.unwrap_or_else(|e| error_response(&e));
+            if conn.responses.send((seq, resp_msg)).is_err() {
                 break;
             }
         }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Replies = serde_json::StreamDeserializer<
+        'static,
+        serde_json::de::IoRead<io::BufReader<UnixStream>>,
+        serde_json::Value,
+    >;
+
+    /// Serves one client over a socket pair, as a server would over its socket.
+    fn serve_pair(jobs: Arc<JobRegistry>) -> (UnixStream, Replies, thread::JoinHandle<()>) {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let (client, server) = UnixStream::pair().unwrap();
+        let server_out = server.try_clone().unwrap();
+        let handle = thread::spawn(move || {
+            handle_client(
+                db,
+                server,
+                server_out,
+                Arc::new(AtomicBool::new(false)),
+                jobs,
+            )
+        });
+        client
+            .set_read_timeout(Some(Duration::from_secs(10)))
+            .unwrap();
+        let replies =
+            serde_json::Deserializer::from_reader(io::BufReader::new(client.try_clone().unwrap()))
+                .into_iter::<serde_json::Value>();
+        (client, replies, handle)
+    }
+
+    fn request(client: &mut UnixStream, replies: &mut Replies, msg: String) -> serde_json::Value {
+        client.write_all(msg.as_bytes()).unwrap();
+        replies.next().unwrap().unwrap()
     }
 
-    // Ignore this error because we are already done communicating with the client.
-    let _ = outstream.flush();
+    fn project_file_changed(seq: u32, path: &Path, contents: &str) -> String {
+        serde_json::json!([
+            seq,
+            {
+                "type": "ProjectFileChangedCmd",
+                "index_name": "app",
+                "path": path,
+                "contents": contents
+            }
+        ])
+        .to_string()
+    }
+
+    fn class_query(seq: u32, class_name: &str) -> String {
+        serde_json::json!([
+            seq,
+            {"type": "ClassQuery", "index_name": "app", "class_name": class_name}
+        ])
+        .to_string()
+    }
+
+    #[test]
+    fn project_file_changed_cmd_updates_the_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Fresh.java");
+        let (mut client, mut replies, handle) = serve_pair(Arc::new(JobRegistry::default()));
+
+        let reply = request(
+            &mut client,
+            &mut replies,
+            project_file_changed(1, &path, "package com.example; public class Fresh {}"),
+        );
+        assert_eq!(reply, serde_json::json!([1, {"type": "NullResponse"}]));
+        let reply = request(&mut client, &mut replies, class_query(2, "Fresh"));
+        assert_eq!(
+            reply[1]["results"],
+            serde_json::json!({"Fresh": ["com.example"]})
+        );
+
+        let reply = request(
+            &mut client,
+            &mut replies,
+            project_file_changed(3, &dir.path().join("Notes.txt"), ""),
+        );
+        assert_eq!(reply[0], 3);
+        assert_eq!(reply[1]["kind"], "InvalidPath");
+
+        client.write_all(br#"[4, {"type":"ShutdownCmd"}]"#).unwrap();
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn project_file_changed_cmd_does_not_wait_for_a_job_on_its_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Fresh.java");
+        let jobs = Arc::new(JobRegistry::default());
+        let (mut client, mut replies, handle) = serve_pair(jobs.clone());
+        // Stands in for a running job that is writing the index.
+        let job_lease = jobs.lease_indexes(&["app".to_string()]);
+
+        let reply = request(
+            &mut client,
+            &mut replies,
+            project_file_changed(1, &path, "package com.example; public class Fresh {}"),
+        );
+        assert_eq!(
+            reply,
+            serde_json::json!([1, {"type": "JobStarted", "job_id": 1}])
+        );
+        let reply = request(&mut client, &mut replies, class_query(2, "Fresh"));
+        assert_eq!(reply[0], 2);
+        assert_eq!(reply[1]["type"], "ClassQueryResponse");
+        assert_eq!(reply[1]["results"], serde_json::json!({"Fresh": []}));
+
+        drop(job_lease);
+        let finished = replies
+            .find(|reply| reply.as_ref().unwrap()[1]["type"] == "JobFinished")
+            .unwrap()
+            .unwrap();
+        assert_eq!(finished[0], 0);
+        assert_eq!(finished[1]["command"], "ProjectFileChangedCmd");
+        assert_eq!(finished[1]["state"], "Finished");
+        let reply = request(&mut client, &mut replies, class_query(3, "Fresh"));
+        assert_eq!(
+            reply[1]["results"],
+            serde_json::json!({"Fresh": ["com.example"]})
+        );
+
+        client.write_all(br#"[4, {"type":"ShutdownCmd"}]"#).unwrap();
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn recover_seq_reads_a_leading_sequence_number() {
+        assert_eq!(recover_seq(b"[12,{\"type\":"), 12);
+        assert_eq!(recover_seq(b"  [ 7 ,\"oops\"]"), 7);
+        assert_eq!(recover_seq(b"[3"), 3);
+    }
+
+    #[test]
+    fn recover_seq_falls_back_to_zero() {
+        assert_eq!(recover_seq(b""), 0);
+        assert_eq!(recover_seq(b"junk"), 0);
+        assert_eq!(recover_seq(b"{\"seq\":5}"), 0);
+        assert_eq!(recover_seq(b"[\"5\"]"), 0);
+        assert_eq!(recover_seq(b"[-1,{}]"), 0);
+        assert_eq!(recover_seq(b"[99999999999,{}]"), 0);
+    }
+
+    #[test]
+    fn a_missing_file_is_an_invalid_path() {
+        let e = Error::new(io::Error::new(io::ErrorKind::NotFound, "No such file"))
+            .context("Could not read /missing/pom.xml");
+        assert_eq!(
+            ErrorResponseArgs::from_error(&e),
+            ErrorResponseArgs {
+                kind: ErrorKind::InvalidPath,
+                message: "Could not read /missing/pom.xml".to_string(),
+                detail: Some("No such file".to_string()),
+            }
+        );
+
+        let e = Error::new(io::Error::new(io::ErrorKind::PermissionDenied, "Denied"));
+        assert_eq!(ErrorResponseArgs::from_error(&e).kind, ErrorKind::Failed);
+    }
+
+    #[test]
+    fn a_request_error_keeps_its_kind_and_detail() {
+        let e: Error = RequestError::new(
+            ErrorKind::UnsupportedMessage,
+            "Unsupported message type: FooCmd".to_string(),
+        )
+        .with_detail("FooCmd".to_string())
+        .into();
+        assert_eq!(
+            ErrorResponseArgs::from_error(&e),
+            ErrorResponseArgs {
+                kind: ErrorKind::UnsupportedMessage,
+                message: "Unsupported message type: FooCmd".to_string(),
+                detail: Some("FooCmd".to_string()),
+            }
+        );
+
+        let e = invalid_path("/tmp/x is not a directory.".to_string()).context("Could not reindex");
+        let args = ErrorResponseArgs::from_error(&e);
+        assert_eq!(args.kind, ErrorKind::InvalidPath);
+        assert_eq!(args.message, "Could not reindex");
+        assert_eq!(args.detail.as_deref(), Some("/tmp/x is not a directory."));
+    }
 }
@@ -14,6 +14,7 @@ extern crate sled;
 
 use anyhow::{anyhow, bail, Context, Error, Result};
 
+use crate::jobs::JobRegistry;
 use crate::proto;
 
 fn serve_accept_loop(
@@ -28,11 +29,13 @@ fn serve_accept_loop(
 
     let listener = UnixListener::bind(&socket_path)?;
     println!("Listening on {}", socket_path);
+    let jobs = Arc::new(JobRegistry::default());
     for client in listener.incoming() {
         match client {
             Ok(mut stream) => {
                 let db1 = db.clone();
                 let shutdown_cond1 = shutdown_cond.clone();
+                let jobs1 = jobs.clone();
                 let mut write_stream = match stream.try_clone() {
                     Ok(s) => s,
                     Err(e) => {
@@ -43,7 +46,13 @@ fn serve_accept_loop(
                     }
                 };
                 thread::spawn(move || {
-                    proto::handle_client(db1, &mut stream, &mut write_stream, shutdown_cond1);
+                    proto::handle_client(
+                        db1,
+                        &mut stream,
+                        &mut write_stream,
+                        shutdown_cond1,
+                        jobs1,
+                    );
                     // Ignore the shutdown errors because we're done with the stream.
                     let _ = stream.shutdown(Shutdown::Both);
                 });
@@ -74,14 +83,15 @@ pub fn serve_unix(db: &sled::Db, socket_path: String) -> Result<()> {
     Ok(())
 }
 
-pub fn serve_stdio<I: Read, O: Write>(
+pub fn serve_stdio<I: Read, O: Write + Send>(
     db: &sled::Db,
     mut instream: I,
     mut outstream: O,
 ) -> Result<()> {
     let shutdown_cond = Arc::new(AtomicBool::new(false));
     let db1 = db.clone();
-    proto::handle_client(db1, &mut instream, &mut outstream, shutdown_cond);
+    let jobs = Arc::new(JobRegistry::default());
+    proto::handle_client(db1, &mut instream, &mut outstream, shutdown_cond, jobs);
     // Ignore the database flush because our only recourse seems to be to die, cutting off other
     // clients too.
     let _ = outstream.flush();
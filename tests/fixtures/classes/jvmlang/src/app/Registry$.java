@@ -0,0 +1,6 @@
+package app;
+
+// The module class scalac emits for `object Registry`.
+public final class Registry$ {
+    public static final Registry$ MODULE$ = new Registry$();
+}
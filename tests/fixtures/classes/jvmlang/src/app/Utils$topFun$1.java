@@ -0,0 +1,7 @@
+package app;
+
+// A lambda class, which kotlinc marks as synthetic.
+@kotlin.Metadata(k = 3)
+final class Utils$topFun$1 {
+    public static void invoke() {}
+}
@@ -0,0 +1,12 @@
+package app;
+
+// What kotlinc emits for a Utils.kt file holding `fun topFun()`, `val maxSize` and
+// `const val LIMIT`.
+@kotlin.Metadata(k = 2, d2 = {"topFun", "", "maxSize", "", "getMaxSize", "()I", "LIMIT"})
+public final class UtilsKt {
+    public static final int LIMIT = 3;
+    public static void topFun() {}
+    public static int getMaxSize() { return 1; }
+    public static void undeclared() {}
+    private static void helper() {}
+}
@@ -0,0 +1,5 @@
+package app;
+
+// A regular Kotlin class; k is left at its default.
+@kotlin.Metadata(d2 = {"Widget", ""})
+public final class Widget {}
@@ -0,0 +1,11 @@
+package kotlin;
+
+import java.lang.annotation.Retention;
+import java.lang.annotation.RetentionPolicy;
+
+// Mirrors the elements of the real kotlin.Metadata that cpid reads.
+@Retention(RetentionPolicy.RUNTIME)
+public @interface Metadata {
+    int k() default 1;
+    String[] d2() default {};
+}
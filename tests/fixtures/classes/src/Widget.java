@@ -0,0 +1,8 @@
+package com.example.api;
+
+@Deprecated(since = "2", forRemoval = true)
+public class Widget {
+    public static final long SIZE = 42L;
+    public static final double RATIO = 0.5;
+    public String name() { return "widget"; }
+}
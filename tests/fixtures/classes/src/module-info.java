@@ -0,0 +1,5 @@
+module com.example.app {
+    requires java.sql;
+    exports com.example.api;
+    exports com.example.internal to java.sql, java.logging;
+}
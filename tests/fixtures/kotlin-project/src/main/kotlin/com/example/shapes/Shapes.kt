@@ -0,0 +1,36 @@
+package com.example.shapes
+
+import kotlin.math.PI
+
+interface Shape {
+    fun area(): Double
+}
+
+class Circle(private val radius: Double) : Shape {
+    override fun area(): Double = PI * radius * radius
+
+    class Builder {
+        fun build(): Circle {
+            class Scratch
+            return Circle(1.0)
+        }
+    }
+
+    companion object {
+        class Hidden
+    }
+}
+
+data class Point(val x: Int, val y: Int)
+
+enum class Color { RED, GREEN }
+
+object Registry {
+    val shapes = mutableListOf<Shape>()
+}
+
+typealias ShapeList = List<Shape>
+
+fun unitCircle(): Circle = Circle(1.0)
+
+val origin = Point(0, 0)
@@ -78,9 +78,25 @@ fn enumerate_sample_project_classpath() -> Result<()> {
         .expect("Reply read failure.")
         .expect("JSON deserialization failure.");
     let expected_reindex_reply: serde_json::Value =
-        serde_json::from_str::<serde_json::Value>(r#"[1,{"type":"NullResponse"}]"#)?;
+        serde_json::from_str::<serde_json::Value>(r#"[1,{"type":"JobStarted","job_id":1}]"#)?;
     assert_eq!(expected_reindex_reply, reindex_reply);
 
+    // Progress notifications precede the one saying the job is over.
+    let finished = loop {
+        let notification = replies
+            .next()
+            .expect("Reply read failure.")
+            .expect("JSON deserialization failure.");
+        assert_eq!(notification[0], 0);
+        if notification[1]["type"] == "JobFinished" {
+            break notification;
+        }
+        assert_eq!(notification[1]["type"], "JobProgress");
+    };
+    assert_eq!(finished[1]["job_id"], 1);
+    assert_eq!(finished[1]["state"], "Finished");
+    assert_eq!(finished[1]["archives_done"], finished[1]["archives_total"]);
+
     let query_msg = r#"[2, {
             "type":"ClassQuery",
             "index_name": "testidx",
@@ -98,7 +114,28 @@ fn enumerate_sample_project_classpath() -> Result<()> {
     )?;
     assert_eq!(expected_qry_reply, qry_reply);
 
-    client_socket.write_all(r#"[3, {"type":"ShutdownCmd"}]"#.as_bytes());
+    client_socket.write_all(r#"[3, {"type":"JobStatusQuery","job_id":1}]"#.as_bytes())?;
+    client_socket.flush()?;
+    let status_reply = replies
+        .next()
+        .expect("Reply read failure.")
+        .expect("JSON deserialization failure.");
+    assert_eq!(status_reply[0], 3);
+    assert_eq!(status_reply[1]["type"], "JobStatusResponse");
+    assert_eq!(status_reply[1]["jobs"][0]["state"], "Finished");
+
+    client_socket.write_all(r#"[4, {"type":"NoSuchQuery"}]"#.as_bytes())?;
+    client_socket.flush()?;
+    let error_reply = replies
+        .next()
+        .expect("Reply read failure.")
+        .expect("JSON deserialization failure.");
+    assert_eq!(error_reply[0], 4);
+    assert_eq!(error_reply[1]["type"], "ErrorResponse");
+    assert_eq!(error_reply[1]["kind"], "UnsupportedMessage");
+    assert_eq!(error_reply[1]["detail"], "NoSuchQuery");
+
+    client_socket.write_all(r#"[5, {"type":"ShutdownCmd"}]"#.as_bytes());
     serve_thread.join();
     Ok(())
 }